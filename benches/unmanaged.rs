@@ -1,9 +1,33 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
 
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+
+use deadpool::managed::{self, RecycleResult};
 use deadpool::unmanaged::Pool;
 
 const ITERATIONS: usize = 1_000_000;
 
+/// Number of `get` calls per task in the contention benchmarks
+const CONTENTION_ITERATIONS: usize = 10_000;
+
+struct Manager {}
+
+#[async_trait]
+impl managed::Manager for Manager {
+    type Type = ();
+    type Error = Infallible;
+    async fn create(&self) -> Result<(), Infallible> {
+        Ok(())
+    }
+    async fn recycle(&self, _: &mut ()) -> RecycleResult<Infallible> {
+        Ok(())
+    }
+}
+
 #[tokio::main]
 async fn use_pool() {
     let pool = Pool::new(16);
@@ -13,9 +37,140 @@ async fn use_pool() {
     }
 }
 
+#[tokio::main]
+async fn use_managed_pool() {
+    let pool = managed::Pool::<Manager>::new(Manager {}, 16);
+    for _ in 0..ITERATIONS {
+        pool.get().await.unwrap();
+    }
+}
+
+/// A pool using a single `Mutex<Vec<T>>` as idle queue like the pools
+/// did before the queues were sharded. It is the baseline of the
+/// contention benchmarks.
+struct MutexPool<T> {
+    queue: Mutex<Vec<T>>,
+    semaphore: Semaphore,
+}
+
+struct MutexObject<'a, T> {
+    pool: &'a MutexPool<T>,
+    obj: Option<T>,
+}
+
+impl<T> MutexPool<T> {
+    fn new(objects: Vec<T>) -> Self {
+        Self {
+            semaphore: Semaphore::new(objects.len()),
+            queue: Mutex::new(objects),
+        }
+    }
+    async fn get(&self) -> MutexObject<'_, T> {
+        self.semaphore.acquire().await.unwrap().forget();
+        let obj = self.queue.lock().unwrap().pop().unwrap();
+        MutexObject {
+            pool: self,
+            obj: Some(obj),
+        }
+    }
+}
+
+impl<T> Drop for MutexObject<'_, T> {
+    fn drop(&mut self) {
+        if let Some(obj) = self.obj.take() {
+            self.pool.queue.lock().unwrap().push(obj);
+            self.pool.semaphore.add_permits(1);
+        }
+    }
+}
+
+fn multi_thread_runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Run `tasks` tasks on all cores which all retrieve objects from the
+/// same pool at the same time.
+async fn contend_unmanaged(pool: &Pool<()>, tasks: usize) {
+    let handles = (0..tasks)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..CONTENTION_ITERATIONS {
+                    pool.get().await.unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+async fn contend_mutex(pool: &Arc<MutexPool<()>>, tasks: usize) {
+    let handles = (0..tasks)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..CONTENTION_ITERATIONS {
+                    pool.get().await;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+async fn contend_managed(pool: &managed::Pool<Manager>, tasks: usize) {
+    let handles = (0..tasks)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..CONTENTION_ITERATIONS {
+                    pool.get().await.unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+fn contention_benchmark(c: &mut Criterion) {
+    let runtime = multi_thread_runtime();
+    let cores = num_cpus::get();
+    let mut group = c.benchmark_group("contention");
+    group.sample_size(10);
+    for &tasks in &[cores, cores * 4] {
+        let pool = Arc::new(MutexPool::new(vec![(); cores]));
+        group.bench_with_input(BenchmarkId::new("mutex", tasks), &tasks, |b, &tasks| {
+            b.iter(|| runtime.block_on(contend_mutex(&pool, tasks)))
+        });
+        let pool = Pool::from(vec![(); cores]);
+        group.bench_with_input(BenchmarkId::new("unmanaged", tasks), &tasks, |b, &tasks| {
+            b.iter(|| runtime.block_on(contend_unmanaged(&pool, tasks)))
+        });
+        let pool = managed::Pool::<Manager>::new(Manager {}, cores);
+        group.bench_with_input(BenchmarkId::new("managed", tasks), &tasks, |b, &tasks| {
+            b.iter(|| runtime.block_on(contend_managed(&pool, tasks)))
+        });
+    }
+    group.finish();
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("use_pool", |b| b.iter(|| use_pool()));
+    c.bench_function("use_pool", |b| b.iter(use_pool));
+    c.bench_function("use_managed_pool", |b| b.iter(use_managed_pool));
 }
 
-criterion_group!(benches, criterion_benchmark);
+// The `mutex` benchmarks of the contention group measure the queue the
+// pools used before sharding. Compare against another revision by
+// running `cargo bench -- --save-baseline <name>` on that revision first
+// and `cargo bench -- --baseline <name>` afterwards.
+criterion_group!(benches, criterion_benchmark, contention_benchmark);
 criterion_main!(benches);
@@ -24,7 +24,7 @@ use crate::{Pool, PoolConfig};
 ///     }
 /// }
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "config", derive(serde::Deserialize))]
 pub struct Config {
     /// AMQP server URL
@@ -59,13 +59,3 @@ impl Config {
         self.pool.clone().unwrap_or_default()
     }
 }
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            url: None,
-            pool: None,
-            connection_properties: lapin::ConnectionProperties::default(),
-        }
-    }
-}
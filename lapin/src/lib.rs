@@ -142,8 +142,8 @@ impl Manager {
     /// Create manager using `PgConfig` and a `TlsConnector`
     pub fn new(addr: String, connection_properties: ConnectionProperties) -> Self {
         Self {
-            addr: addr,
-            connection_properties: connection_properties,
+            addr,
+            connection_properties,
         }
     }
 }
@@ -2,6 +2,7 @@
 
 use std::env;
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
 use tokio_postgres::config::{
@@ -11,7 +12,7 @@ use tokio_postgres::config::{
 use tokio_postgres::tls::{MakeTlsConnect, TlsConnect};
 use tokio_postgres::Socket;
 
-use crate::{Pool, PoolConfig};
+use crate::{ManagerError, PasswordProvider, Pool, PoolConfig};
 
 /// An error which is returned by `Config::create_pool` if something is
 /// wrong with the configuration.
@@ -30,10 +31,10 @@ impl fmt::Display for ConfigError {
 }
 
 #[cfg(feature = "config")]
-impl Into<::config_crate::ConfigError> for ConfigError {
-    fn into(self) -> ::config_crate::ConfigError {
-        match self {
-            Self::Message(message) => ::config_crate::ConfigError::Message(message),
+impl From<ConfigError> for ::config_crate::ConfigError {
+    fn from(val: ConfigError) -> Self {
+        match val {
+            ConfigError::Message(message) => ::config_crate::ConfigError::Message(message),
         }
     }
 }
@@ -51,11 +52,11 @@ pub enum TargetSessionAttrs {
     ReadWrite,
 }
 
-impl Into<PgTargetSessionAttrs> for TargetSessionAttrs {
-    fn into(self) -> PgTargetSessionAttrs {
-        match self {
-            Self::Any => PgTargetSessionAttrs::Any,
-            Self::ReadWrite => PgTargetSessionAttrs::ReadWrite,
+impl From<TargetSessionAttrs> for PgTargetSessionAttrs {
+    fn from(val: TargetSessionAttrs) -> Self {
+        match val {
+            TargetSessionAttrs::Any => PgTargetSessionAttrs::Any,
+            TargetSessionAttrs::ReadWrite => PgTargetSessionAttrs::ReadWrite,
         }
     }
 }
@@ -73,12 +74,12 @@ pub enum SslMode {
     Require,
 }
 
-impl Into<PgSslMode> for SslMode {
-    fn into(self) -> PgSslMode {
-        match self {
-            Self::Disable => PgSslMode::Disable,
-            Self::Prefer => PgSslMode::Prefer,
-            Self::Require => PgSslMode::Require,
+impl From<SslMode> for PgSslMode {
+    fn from(val: SslMode) -> Self {
+        match val {
+            SslMode::Disable => PgSslMode::Disable,
+            SslMode::Prefer => PgSslMode::Prefer,
+            SslMode::Require => PgSslMode::Require,
         }
     }
 }
@@ -96,12 +97,12 @@ pub enum ChannelBinding {
     Require,
 }
 
-impl Into<PgChannelBinding> for ChannelBinding {
-    fn into(self) -> PgChannelBinding {
-        match self {
-            Self::Disable => PgChannelBinding::Disable,
-            Self::Prefer => PgChannelBinding::Prefer,
-            Self::Require => PgChannelBinding::Require,
+impl From<ChannelBinding> for PgChannelBinding {
+    fn from(val: ChannelBinding) -> Self {
+        match val {
+            ChannelBinding::Disable => PgChannelBinding::Disable,
+            ChannelBinding::Prefer => PgChannelBinding::Prefer,
+            ChannelBinding::Require => PgChannelBinding::Require,
         }
     }
 }
@@ -111,7 +112,7 @@ impl Into<PgChannelBinding> for ChannelBinding {
 /// to `Fast` in the next minor release of `deadpool-postgres`. Please
 /// make sure to explicitly state this if you want to keep using the
 /// `Verified` recycling method.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "config", derive(serde::Deserialize))]
 pub enum RecyclingMethod {
     /// Only run `Client::is_closed` when recycling existing connections.
@@ -124,6 +125,7 @@ pub enum RecyclingMethod {
     /// network connections) it is possible that `Client::is_closed` returns
     /// `false` but the connection is dead. You will receive an error on
     /// your first query then.
+    #[default]
     Verified,
     /// Like `Verified` query method but instead use the following sequence of
     /// statements which guarantees a prestine connection:
@@ -163,17 +165,11 @@ impl RecyclingMethod {
             Self::Fast => None,
             Self::Verified => Some(""),
             Self::Clean => Some(DISCARD_SQL),
-            Self::Custom(sql) => Some(&sql),
+            Self::Custom(sql) => Some(sql),
         }
     }
 }
 
-impl Default for RecyclingMethod {
-    fn default() -> Self {
-        Self::Verified
-    }
-}
-
 #[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "config", derive(serde::Deserialize))]
 /// Configuration object for the manager. This currently only makes it
@@ -279,6 +275,27 @@ impl Config {
         let pool_config = self.get_pool_config();
         Ok(Pool::from_config(manager, pool_config))
     }
+    /// Create pool using the current configuration which asks `provider`
+    /// for the password before each new connection. The password
+    /// overrides `password`. See `Manager::with_password_provider`.
+    pub fn create_pool_with_password_provider<T>(
+        &self,
+        tls: T,
+        provider: Arc<dyn PasswordProvider>,
+    ) -> Result<Pool<T, ManagerError>, ConfigError>
+    where
+        T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+        T::Stream: Sync + Send,
+        T::TlsConnect: Sync + Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        let pg_config = self.get_pg_config()?;
+        let manager_config = self.get_manager_config();
+        let manager = crate::Manager::from_config(pg_config, tls, manager_config)
+            .with_password_provider(provider);
+        let pool_config = self.get_pool_config();
+        Ok(Pool::from_config(manager, pool_config))
+    }
     /// Get `tokio_postgres::Config` which can be used to connect to
     /// the database.
     pub fn get_pg_config(&self) -> Result<tokio_postgres::Config, ConfigError> {
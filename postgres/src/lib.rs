@@ -175,6 +175,8 @@
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock, Weak};
@@ -189,8 +191,12 @@ use tokio_postgres::{
     TransactionBuilder as PgTransactionBuilder,
 };
 
+use crate::provider::PasswordCache;
+
 pub mod config;
 pub use crate::config::{Config, ManagerConfig, RecyclingMethod};
+pub mod provider;
+pub use crate::provider::{Password, PasswordProvider, ProviderError};
 
 /// Re-export deadpool::managed::PoolConfig
 pub use deadpool::managed::PoolConfig;
@@ -198,28 +204,67 @@ pub use deadpool::managed::PoolConfig;
 pub use deadpool::Runtime;
 
 /// A type alias for using `deadpool::Pool` with `tokio_postgres`
-pub type Pool<T> = deadpool::managed::Pool<Manager<T>>;
+pub type Pool<T, E = Error> = deadpool::managed::Pool<Manager<T, E>>;
 
 /// A type alias for using `deadpool::PoolError` with `tokio_postgres`
-pub type PoolError = deadpool::managed::PoolError<tokio_postgres::Error>;
+pub type PoolError<E = Error> = deadpool::managed::PoolError<E>;
 
 /// A type alias for using `deadpool::Object` with `tokio_postgres`
-pub type Client<T> = deadpool::managed::Object<Manager<T>>;
+pub type Client<T, E = Error> = deadpool::managed::Object<Manager<T, E>>;
 
-type RecycleResult = deadpool::managed::RecycleResult<Error>;
-type RecycleError = deadpool::managed::RecycleError<Error>;
+type RecycleResult<E = Error> = deadpool::managed::RecycleResult<E>;
+type RecycleError<E = Error> = deadpool::managed::RecycleError<E>;
 
 /// Re-export tokio_postgres crate
 pub use tokio_postgres;
 
+/// The error type returned by a `Manager` which uses a `PasswordProvider`.
+/// See `Manager::with_password_provider`.
+#[derive(Debug)]
+pub enum ManagerError {
+    /// The database returned an error
+    Backend(Error),
+    /// The `PasswordProvider` failed to provide a password
+    Provider(ProviderError),
+}
+
+impl From<Error> for ManagerError {
+    fn from(e: Error) -> Self {
+        Self::Backend(e)
+    }
+}
+
+impl fmt::Display for ManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Backend(e) => write!(f, "{}", e),
+            Self::Provider(e) => write!(f, "Password provider failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ManagerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Backend(e) => Some(e),
+            Self::Provider(e) => Some(&**e),
+        }
+    }
+}
+
 /// The manager for creating and recyling postgresql connections
-pub struct Manager<T: MakeTlsConnect<Socket>> {
+///
+/// Its errors are `tokio_postgres::Error`s unless a `PasswordProvider`
+/// is used which changes the error type to `ManagerError`.
+pub struct Manager<T: MakeTlsConnect<Socket>, E = Error> {
     config: ManagerConfig,
     pg_config: PgConfig,
     tls: T,
+    password_cache: Option<PasswordCache>,
     /// This field provides access to the statement caches of clients
     /// handed out by the pool.
     pub statement_caches: StatementCaches,
+    _error: PhantomData<fn() -> E>,
 }
 
 impl<T: MakeTlsConnect<Socket>> Manager<T> {
@@ -238,24 +283,41 @@ impl<T: MakeTlsConnect<Socket>> Manager<T> {
             config,
             pg_config,
             tls,
+            password_cache: None,
             statement_caches: StatementCaches::default(),
+            _error: PhantomData,
+        }
+    }
+    /// Use the given `PasswordProvider` to obtain the password before
+    /// each new connection is established. The password is cached until
+    /// it expires. It overrides the password of the `tokio_postgres::Config`.
+    ///
+    /// The returned manager reports errors of the provider and the
+    /// database as `ManagerError`.
+    pub fn with_password_provider(
+        self,
+        provider: Arc<dyn PasswordProvider>,
+    ) -> Manager<T, ManagerError> {
+        Manager {
+            config: self.config,
+            pg_config: self.pg_config,
+            tls: self.tls,
+            password_cache: Some(PasswordCache::new(provider)),
+            statement_caches: self.statement_caches,
+            _error: PhantomData,
         }
     }
 }
 
-#[async_trait]
-impl<T> deadpool::managed::Manager for Manager<T>
+impl<T, E> Manager<T, E>
 where
     T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
     T::Stream: Sync + Send,
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    type Type = ClientWrapper;
-    type Error = Error;
-
-    async fn create(&self) -> Result<ClientWrapper, Error> {
-        let (client, connection) = self.pg_config.connect(self.tls.clone()).await?;
+    async fn connect(&self, pg_config: &PgConfig) -> Result<ClientWrapper, Error> {
+        let (client, connection) = pg_config.connect(self.tls.clone()).await?;
         let connection = connection.map(|r| {
             if let Err(e) = r {
                 warn!(target: "deadpool.postgres", "Connection error: {}", e);
@@ -267,7 +329,7 @@ where
             .attach(&client_wrapper.statement_cache);
         Ok(client_wrapper)
     }
-    async fn recycle(&self, client: &mut ClientWrapper) -> RecycleResult {
+    async fn recycle_client(&self, client: &mut ClientWrapper) -> RecycleResult {
         if client.is_closed() {
             info!(target: "deadpool.postgres", "Connection could not be recycled: Connection closed");
             return Err(RecycleError::Message("Connection closed".to_string()));
@@ -283,6 +345,59 @@ where
             None => Ok(()),
         }
     }
+}
+
+#[async_trait]
+impl<T> deadpool::managed::Manager for Manager<T>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    type Type = ClientWrapper;
+    type Error = Error;
+
+    async fn create(&self) -> Result<ClientWrapper, Error> {
+        self.connect(&self.pg_config).await
+    }
+    async fn recycle(&self, client: &mut ClientWrapper) -> RecycleResult {
+        self.recycle_client(client).await
+    }
+    fn detach(&self, object: &mut ClientWrapper) {
+        self.statement_caches.detach(&object.statement_cache);
+    }
+}
+
+#[async_trait]
+impl<T> deadpool::managed::Manager for Manager<T, ManagerError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    type Type = ClientWrapper;
+    type Error = ManagerError;
+
+    async fn create(&self) -> Result<ClientWrapper, ManagerError> {
+        let client = match &self.password_cache {
+            Some(cache) => {
+                let password = cache.password().await.map_err(ManagerError::Provider)?;
+                let mut pg_config = self.pg_config.clone();
+                pg_config.password(password);
+                self.connect(&pg_config).await?
+            }
+            None => self.connect(&self.pg_config).await?,
+        };
+        Ok(client)
+    }
+    async fn recycle(&self, client: &mut ClientWrapper) -> RecycleResult<ManagerError> {
+        self.recycle_client(client).await.map_err(|e| match e {
+            RecycleError::Message(msg) => RecycleError::Message(msg),
+            RecycleError::Backend(e) => RecycleError::Backend(e.into()),
+        })
+    }
     fn detach(&self, object: &mut ClientWrapper) {
         self.statement_caches.detach(&object.statement_cache);
     }
@@ -297,11 +412,11 @@ pub struct StatementCaches {
 
 impl StatementCaches {
     fn attach(&self, cache: &Arc<StatementCache>) {
-        let cache = Arc::downgrade(&cache);
+        let cache = Arc::downgrade(cache);
         self.caches.lock().unwrap().push(cache)
     }
     fn detach(&self, cache: &Arc<StatementCache>) {
-        let cache = Arc::downgrade(&cache);
+        let cache = Arc::downgrade(cache);
         self.caches.lock().unwrap().retain(|sc| !sc.ptr_eq(&cache));
     }
     /// Clear statement cache of all connections which were handed out by
@@ -382,7 +497,7 @@ impl StatementCache {
         removed
     }
     /// Get statement from cache
-    fn get<'a>(&self, query: &str, types: &[Type]) -> Option<Statement> {
+    fn get(&self, query: &str, types: &[Type]) -> Option<Statement> {
         let key = StatementCacheKey {
             query: Cow::Borrowed(query),
             types: Cow::Borrowed(types),
@@ -455,7 +570,7 @@ impl ClientWrapper {
     /// transaction's isolation level and other attributes.
     ///
     /// See [`tokio_postgres::Client::transaction_builder`](#method.transaction_builder-1)
-    pub fn build_transaction(&mut self) -> TransactionBuilder {
+    pub fn build_transaction(&mut self) -> TransactionBuilder<'_> {
         TransactionBuilder {
             builder: self.client.build_transaction(),
             statement_cache: &self.statement_cache,
@@ -516,7 +631,7 @@ impl<'a> Transaction<'a> {
     pub async fn transaction<'b>(&'b mut self) -> Result<Transaction<'b>, Error> {
         Ok(Transaction {
             txn: PgTransaction::transaction(&mut self.txn).await?,
-            statement_cache: &mut self.statement_cache,
+            statement_cache: self.statement_cache,
         })
     }
 }
@@ -0,0 +1,85 @@
+//! This module provides support for passwords which change over time
+//! (e.g. short-lived authentication tokens).
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+/// The error type which can be returned by a `PasswordProvider`
+pub type ProviderError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A password returned by a `PasswordProvider`
+#[derive(Clone, Debug)]
+pub struct Password {
+    /// The password used to connect to the database
+    pub password: String,
+    /// The password is reused for new connections until this point in
+    /// time. If `None` the provider is asked again for every new
+    /// connection.
+    pub expires_at: Option<Instant>,
+}
+
+impl Password {
+    /// Create password which is cached until `expires_at`
+    pub fn new(password: impl Into<String>, expires_at: Option<Instant>) -> Self {
+        Self {
+            password: password.into(),
+            expires_at,
+        }
+    }
+    fn is_valid(&self) -> bool {
+        matches!(self.expires_at, Some(expires_at) if Instant::now() < expires_at)
+    }
+}
+
+/// This trait is used to obtain the current password before a new
+/// connection is established. It is meant for authentication methods
+/// using short-lived tokens which can't be stored in the configuration.
+///
+/// Existing connections are not affected when a password expires.
+/// Providers should return an `expires_at` which leaves enough time
+/// to establish a connection.
+#[async_trait]
+pub trait PasswordProvider: Send + Sync {
+    /// Get the current password
+    async fn password(&self) -> Result<Password, ProviderError>;
+}
+
+impl fmt::Debug for dyn PasswordProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PasswordProvider")
+    }
+}
+
+/// Caches the passwords of a `PasswordProvider` until they expire.
+pub(crate) struct PasswordCache {
+    provider: Arc<dyn PasswordProvider>,
+    cached: Mutex<Option<Password>>,
+}
+
+impl PasswordCache {
+    pub(crate) fn new(provider: Arc<dyn PasswordProvider>) -> Self {
+        Self {
+            provider,
+            cached: Mutex::new(None),
+        }
+    }
+    /// Get the cached password or ask the provider for a new one. The
+    /// lock is held while waiting for the provider so that concurrent
+    /// connects don't all call the provider at once.
+    pub(crate) async fn password(&self) -> Result<String, ProviderError> {
+        let mut cached = self.cached.lock().await;
+        match &*cached {
+            Some(password) if password.is_valid() => Ok(password.password.clone()),
+            _ => {
+                let password = self.provider.password().await?;
+                let value = password.password.clone();
+                *cached = Some(password);
+                Ok(value)
+            }
+        }
+    }
+}
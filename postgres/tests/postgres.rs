@@ -1,12 +1,17 @@
 use std::collections::HashMap;
 use std::env;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures::future::join_all;
 use serde::Deserialize;
 use tokio_postgres::{types::Type, IsolationLevel};
 
-use deadpool_postgres::{ManagerConfig, Pool, RecyclingMethod};
+use deadpool_postgres::{
+    ManagerConfig, ManagerError, Password, PasswordProvider, Pool, PoolError, ProviderError,
+    RecyclingMethod,
+};
 
 #[derive(Debug, Deserialize)]
 struct Config {
@@ -120,8 +125,8 @@ async fn test_transaction_pipeline() {
         });
     }
     let results = join_all(futures).await;
-    for i in 0..100 {
-        assert_eq!(results[i], (i as i32) + 1);
+    for (i, result) in results.into_iter().enumerate() {
+        assert_eq!(result, (i as i32) + 1);
     }
 }
 
@@ -205,6 +210,63 @@ async fn test_statement_caches_clear() {
     assert!(client1.statement_cache.size() == 0);
 }
 
+struct Provider {
+    password: Option<String>,
+    expires_in: Option<Duration>,
+    calls: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl PasswordProvider for Provider {
+    async fn password(&self) -> Result<Password, ProviderError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match &self.password {
+            Some(password) => Ok(Password::new(
+                password.as_str(),
+                self.expires_in.map(|d| Instant::now() + d),
+            )),
+            None => Err("no password".into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_password_provider_error() {
+    let cfg = Config::from_env();
+    let provider = Arc::new(Provider {
+        password: None,
+        expires_in: None,
+        calls: AtomicUsize::new(0),
+    });
+    let pool = cfg
+        .pg
+        .create_pool_with_password_provider(tokio_postgres::NoTls, provider)
+        .unwrap();
+    assert!(matches!(
+        pool.get().await,
+        Err(PoolError::Backend(ManagerError::Provider(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_password_provider_cache() {
+    for (expires_in, expected_calls) in &[(Some(Duration::from_secs(60)), 1), (None, 2)] {
+        let cfg = Config::from_env();
+        let provider = Arc::new(Provider {
+            password: Some(cfg.pg.password.clone().unwrap_or_default()),
+            expires_in: *expires_in,
+            calls: AtomicUsize::new(0),
+        });
+        let pool = cfg
+            .pg
+            .create_pool_with_password_provider(tokio_postgres::NoTls, provider.clone())
+            .unwrap();
+        let _client0 = pool.get().await.unwrap();
+        let _client1 = pool.get().await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::Relaxed), *expected_calls);
+    }
+}
+
 struct Env {
     backup: HashMap<String, Option<String>>,
 }
@@ -2,30 +2,6 @@ use std::fmt;
 
 use crate::{Pool, PoolConfig, RedisResult};
 
-/// Configuration object. By enabling the `config` feature you can
-/// read the configuration using the [`config`](https://crates.io/crates/config)
-/// crate.
-/// ## Example environment
-/// ```env
-/// REDIS__CONNECTION__ADDR=redis.example.com
-/// REDIS__POOL__MAX_SIZE=16
-/// REDIS__POOL__TIMEOUTS__WAIT__SECS=2
-/// REDIS__POOL__TIMEOUTS__WAIT__NANOS=0
-/// ```
-/// ## Example usage
-/// ```rust,ignore
-/// struct Config {
-///     redis: deadpool_postgres::Config,
-/// }
-/// impl Config {
-///     pub fn from_env() -> Result<Self, config::ConfigError> {
-///         let mut cfg = config::Config::new();
-///         cfg.merge(config::Environment::new().separator("__")).unwrap();
-///         cfg.try_into().unwrap()
-///     }
-/// }
-/// ```
-
 /// This is a 1:1 copy of the `redis::ConnectionAddr` enumeration.
 /// This is duplicated here in order to add support for the
 /// `serde::Deserialize` trait which is required for the `config`
@@ -48,11 +24,11 @@ impl Default for ConnectionAddr {
     }
 }
 
-impl Into<redis::ConnectionAddr> for ConnectionAddr {
-    fn into(self) -> redis::ConnectionAddr {
-        match self {
-            Self::Tcp(host, port) => redis::ConnectionAddr::Tcp(host, port),
-            Self::TcpTls {
+impl From<ConnectionAddr> for redis::ConnectionAddr {
+    fn from(val: ConnectionAddr) -> Self {
+        match val {
+            ConnectionAddr::Tcp(host, port) => redis::ConnectionAddr::Tcp(host, port),
+            ConnectionAddr::TcpTls {
                 host,
                 port,
                 insecure,
@@ -61,14 +37,14 @@ impl Into<redis::ConnectionAddr> for ConnectionAddr {
                 port,
                 insecure,
             },
-            Self::Unix(path) => redis::ConnectionAddr::Unix(path),
+            ConnectionAddr::Unix(path) => redis::ConnectionAddr::Unix(path),
         }
     }
 }
 
-impl Into<ConnectionAddr> for redis::ConnectionAddr {
-    fn into(self) -> ConnectionAddr {
-        match self {
+impl From<redis::ConnectionAddr> for ConnectionAddr {
+    fn from(val: redis::ConnectionAddr) -> Self {
+        match val {
             redis::ConnectionAddr::Tcp(host, port) => ConnectionAddr::Tcp(host, port),
             redis::ConnectionAddr::TcpTls {
                 host,
@@ -97,24 +73,24 @@ pub struct ConnectionInfo {
     passwd: Option<String>,
 }
 
-impl Into<redis::ConnectionInfo> for ConnectionInfo {
-    fn into(self) -> redis::ConnectionInfo {
+impl From<ConnectionInfo> for redis::ConnectionInfo {
+    fn from(val: ConnectionInfo) -> Self {
         redis::ConnectionInfo {
-            addr: Box::new((*self.addr).into()),
-            db: self.db,
-            username: self.username,
-            passwd: self.passwd,
+            addr: Box::new((*val.addr).into()),
+            db: val.db,
+            username: val.username,
+            passwd: val.passwd,
         }
     }
 }
 
-impl Into<ConnectionInfo> for redis::ConnectionInfo {
-    fn into(self) -> ConnectionInfo {
+impl From<redis::ConnectionInfo> for ConnectionInfo {
+    fn from(val: redis::ConnectionInfo) -> Self {
         ConnectionInfo {
-            addr: Box::new((*self.addr).into()),
-            db: self.db,
-            username: self.username,
-            passwd: self.passwd,
+            addr: Box::new((*val.addr).into()),
+            db: val.db,
+            username: val.username,
+            passwd: val.passwd,
         }
     }
 }
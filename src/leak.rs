@@ -0,0 +1,139 @@
+//! Leak detection for objects which are checked out for too long
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use crate::runtime::{Runtime, TimeoutError};
+
+/// An object which is currently checked out from a pool with leak
+/// detection enabled.
+#[derive(Clone, Debug)]
+pub struct CheckedOut {
+    /// The location of the `get` call which checked out the object
+    pub location: &'static Location<'static>,
+    /// The duration the object has been checked out for
+    pub duration: Duration,
+}
+
+struct Checkout {
+    location: &'static Location<'static>,
+    since: Instant,
+    reported: bool,
+}
+
+/// Keeps track of the caller locations of all checked out objects.
+pub(crate) struct LeakDetector {
+    threshold: Duration,
+    next_id: AtomicU64,
+    checkouts: Mutex<HashMap<u64, Checkout>>,
+}
+
+impl LeakDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            next_id: AtomicU64::new(0),
+            checkouts: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Register a checkout returning the id which must be passed to
+    /// `checkin` when the object is returned.
+    pub fn checkout(&self, location: &'static Location<'static>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut checkouts = self.checkouts.lock().unwrap();
+        checkouts.insert(
+            id,
+            Checkout {
+                location,
+                since: Instant::now(),
+                reported: false,
+            },
+        );
+        id
+    }
+    /// Unregister a checkout and warn if the object was held for longer
+    /// than the threshold.
+    pub fn checkin(&self, id: u64) {
+        let checkout = self.checkouts.lock().unwrap().remove(&id);
+        if let Some(checkout) = checkout {
+            let duration = checkout.since.elapsed();
+            if duration > self.threshold {
+                log::warn!(
+                    "Object checked out at {} was returned after {:?}",
+                    checkout.location,
+                    duration
+                );
+            }
+        }
+    }
+    /// Warn about all objects which are checked out for longer than
+    /// the threshold. Every checkout is only reported once.
+    pub fn report(&self) {
+        let mut checkouts = self.checkouts.lock().unwrap();
+        for checkout in checkouts.values_mut() {
+            let duration = checkout.since.elapsed();
+            if !checkout.reported && duration > self.threshold {
+                checkout.reported = true;
+                log::warn!(
+                    "Object checked out at {} is still in use after {:?}",
+                    checkout.location,
+                    duration
+                );
+            }
+        }
+    }
+    /// Drive `future` which waits for an object to become available.
+    /// Leaked objects are reported as soon as the caller has to wait and
+    /// then every time the threshold elapses until `future` completes.
+    /// Without a runtime only the first check is performed.
+    pub async fn watch<F: Future>(&self, runtime: &Runtime, future: F) -> F::Output {
+        tokio::pin!(future);
+        if let Poll::Ready(output) = poll_fn(|cx| Poll::Ready(future.as_mut().poll(cx))).await {
+            return output;
+        }
+        loop {
+            self.report();
+            match runtime.timeout(self.threshold, future.as_mut()).await {
+                Ok(output) => return output,
+                Err(TimeoutError::Timeout) => continue,
+                Err(TimeoutError::NoRuntime) => return future.await,
+            }
+        }
+    }
+    /// List all checked out objects starting with the one which has
+    /// been checked out the longest.
+    pub fn checked_out(&self) -> Vec<CheckedOut> {
+        let mut checkouts = self
+            .checkouts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, checkout)| (checkout.since, *id, checkout.location))
+            .collect::<Vec<_>>();
+        checkouts.sort();
+        checkouts
+            .into_iter()
+            .map(|(since, _, location)| CheckedOut {
+                location,
+                duration: since.elapsed(),
+            })
+            .collect()
+    }
+}
+
+/// Drive `future` using `LeakDetector::watch` if leak detection is
+/// enabled.
+pub(crate) async fn watch<F: Future>(
+    leak_detector: Option<&LeakDetector>,
+    runtime: &Runtime,
+    future: F,
+) -> F::Output {
+    match leak_detector {
+        Some(leak_detector) => leak_detector.watch(runtime, future).await,
+        None => future.await,
+    }
+}
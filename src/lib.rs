@@ -24,7 +24,7 @@
 //! | `managed` | Enable managed pool implementation | `async-trait` | yes |
 //! | `unmanaged` | Enable unmanaged pool implementation | - | yes |
 //! | `config` | Enable support for [config](https://crates.io/crates/config) crate | `config`, `serde/derive` | yes |
-//! | `rt_tokio_1` | Enable support for [tokio](https://crates.io/crates/tokio) crate | `tokio/time` | no |
+//! | `rt_tokio_1` | Enable support for [tokio](https://crates.io/crates/tokio) crate | `tokio/time`, `tokio/rt` | no |
 //! | `rt_async-std_1` | Enable support for [async-std](https://crates.io/crates/config) crate | `async-std` | no |
 //!
 //! The runtime features (`rt_*`) are only needed if you need support for
@@ -184,9 +184,20 @@ pub mod managed;
 #[cfg(feature = "unmanaged")]
 pub mod unmanaged;
 
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+mod leak;
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+pub use leak::CheckedOut;
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+mod observer;
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+mod queue;
+
 mod runtime;
 pub use runtime::Runtime;
 
+use std::time::{Duration, Instant};
+
 #[derive(Debug)]
 /// The current pool status.
 pub struct Status {
@@ -198,4 +209,101 @@ pub struct Status {
     /// objects in the pool this number can become negative and stores the
     /// number of futures waiting for an object.
     pub available: isize,
+    /// The number of objects which were discarded instead of being
+    /// returned to the pool because they were poisoned. This is always
+    /// zero for unmanaged pools.
+    pub poisoned: usize,
+    /// The sum of the weights of all objects which are currently checked
+    /// out. Every object has a weight of one unless it was retrieved using
+    /// `managed::Pool::get_weighted`.
+    pub used_weight: usize,
+    /// The number of futures waiting for an object per priority. This is
+    /// always zero for unmanaged pools.
+    pub waiting: Waiting,
+    /// The number of slots exceeding `max_size` which are currently in
+    /// use. See `managed::PoolConfig::overflow`. This is always zero for
+    /// unmanaged pools.
+    pub overflow: usize,
+}
+
+/// The number of futures waiting for an object per priority. See
+/// `managed::Pool::get_with_priority`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Waiting {
+    /// Waiting with `Priority::Low`
+    pub low: usize,
+    /// Waiting with `Priority::Normal`
+    pub normal: usize,
+    /// Waiting with `Priority::High`
+    pub high: usize,
+}
+
+/// Statistics about an object of the pool
+#[derive(Clone, Copy, Debug)]
+pub struct Metrics {
+    /// The instant when the object was created. For unmanaged pools this
+    /// is the instant when the object was added to the pool.
+    pub created: Instant,
+    /// The instant when the object was last recycled. For unmanaged pools
+    /// this is the instant when the object was last returned to the pool.
+    pub recycled: Option<Instant>,
+    /// The number of times the object was recycled
+    pub recycle_count: usize,
+}
+
+impl Metrics {
+    #[cfg(any(feature = "managed", feature = "unmanaged"))]
+    pub(crate) fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            recycled: None,
+            recycle_count: 0,
+        }
+    }
+    #[cfg(any(feature = "managed", feature = "unmanaged"))]
+    pub(crate) fn recycle(&mut self) {
+        self.recycled = Some(Instant::now());
+        self.recycle_count += 1;
+    }
+    /// Get the time elapsed since the object was created
+    pub fn age(&self) -> Duration {
+        self.created.elapsed()
+    }
+    /// Get the time elapsed since the object was last recycled or created
+    /// if it was never recycled
+    pub fn last_used(&self) -> Duration {
+        self.recycled.unwrap_or(self.created).elapsed()
+    }
+}
+
+/// Error structure returned by the `build` method of the pool builders.
+#[derive(Debug)]
+pub enum BuildError {
+    /// The `max_size` of the pool was set to zero.
+    ZeroMaxSize,
+    /// The `max_concurrent_creates` of the pool was set to zero which
+    /// would prevent any object from being created.
+    ZeroMaxConcurrentCreates,
+    /// A timeout was configured but no runtime was specified. The
+    /// contained string names the timeout in question.
+    NoRuntimeSpecified(&'static str),
 }
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ZeroMaxSize => write!(f, "The max_size of the pool must not be zero."),
+            Self::ZeroMaxConcurrentCreates => write!(
+                f,
+                "The max_concurrent_creates of the pool must not be zero."
+            ),
+            Self::NoRuntimeSpecified(timeout) => write!(
+                f,
+                "The {} timeout is configured but no runtime specified.",
+                timeout
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
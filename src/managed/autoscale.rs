@@ -0,0 +1,229 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Configuration of the adaptive sizing of a managed pool. See
+/// `PoolBuilder::autoscale`.
+///
+/// The pool starts with a limit of `min_size` objects. The limit is
+/// increased by `step` if the calls to `Pool::get` during the last
+/// `window` waited longer than `grow_threshold` on average. Calls which
+/// are still waiting are included with the time they waited so far. If
+/// some slots stayed unused during the whole window and no call is
+/// waiting the limit is decreased by up to `step` instead and surplus
+/// idle objects are dropped. The limit never exceeds
+/// `PoolConfig::max_size` and is changed at most once per window.
+///
+/// Decisions are made whenever `Pool::get` is called or returns. If a
+/// runtime is configured they are also made periodically while calls
+/// are waiting. The limit of a pool which isn't used doesn't change.
+#[derive(Clone, Debug)]
+pub struct AutoscaleConfig {
+    /// Lower bound of the pool limit. Values below one are treated as
+    /// one.
+    pub min_size: usize,
+    /// Duration over which wait times and unused slots are observed
+    /// before the limit is adjusted
+    pub window: Duration,
+    /// The limit is increased if the average time `Pool::get` waited
+    /// for a slot exceeds this duration
+    pub grow_threshold: Duration,
+    /// Number of slots which are added or removed at once
+    pub step: usize,
+}
+
+impl AutoscaleConfig {
+    /// Create autoscale config with a window of ten seconds, a grow
+    /// threshold of ten milliseconds and a step of one.
+    pub fn new(min_size: usize) -> Self {
+        Self {
+            min_size,
+            window: Duration::from_secs(10),
+            grow_threshold: Duration::from_millis(10),
+            step: 1,
+        }
+    }
+}
+
+/// Number of buckets a window is divided into. Observations older than
+/// a window are discarded bucket by bucket.
+const BUCKETS: usize = 10;
+
+/// Observations of a fraction of the window
+#[derive(Clone, Copy)]
+struct Bucket {
+    /// Number of the bucket counted from `State::started`. `u64::MAX`
+    /// marks a bucket which has never been used.
+    number: u64,
+    gets: u32,
+    waited: Duration,
+    /// The fewest unused slots observed during the bucket
+    min_unused: usize,
+}
+
+impl Bucket {
+    const EMPTY: Bucket = Bucket {
+        number: u64::MAX,
+        gets: 0,
+        waited: Duration::from_secs(0),
+        min_unused: usize::MAX,
+    };
+}
+
+struct State {
+    /// Start of the first bucket
+    started: Instant,
+    buckets: [Bucket; BUCKETS],
+    /// Number of calls which are currently waiting
+    pending: u32,
+    /// Sum of the start times of the pending calls relative to `started`
+    pending_since: Duration,
+    /// The first bucket which may be used for the next decision. Only
+    /// observations made after the last decision are considered.
+    first: u64,
+}
+
+impl State {
+    fn bucket(&mut self, number: u64) -> &mut Bucket {
+        let bucket = &mut self.buckets[(number % BUCKETS as u64) as usize];
+        if bucket.number != number {
+            *bucket = Bucket {
+                number,
+                ..Bucket::EMPTY
+            };
+        }
+        bucket
+    }
+}
+
+/// Decides about the limit of the pool based on the observations of
+/// the last window.
+pub(crate) struct Autoscaler {
+    config: AutoscaleConfig,
+    max_size: usize,
+    /// Duration of a bucket
+    interval: Duration,
+    state: Mutex<State>,
+}
+
+/// A call to `Pool::get` which is waiting for a slot. The wait time is
+/// recorded when it is dropped.
+pub(crate) struct Wait<'a> {
+    autoscaler: &'a Autoscaler,
+    started: Instant,
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        self.autoscaler.finish(self.started);
+    }
+}
+
+impl Autoscaler {
+    pub(crate) fn new(mut config: AutoscaleConfig, max_size: usize) -> Self {
+        config.min_size = config.min_size.clamp(1, max_size.max(1));
+        config.step = config.step.max(1);
+        let interval = (config.window / BUCKETS as u32).max(Duration::from_nanos(1));
+        Self {
+            config,
+            max_size,
+            interval,
+            state: Mutex::new(State {
+                started: Instant::now(),
+                buckets: [Bucket::EMPTY; BUCKETS],
+                pending: 0,
+                pending_since: Duration::from_secs(0),
+                first: 0,
+            }),
+        }
+    }
+    /// The limit the pool starts with
+    pub(crate) fn initial_limit(&self) -> usize {
+        self.config.min_size
+    }
+    /// The interval in which the limit is re-evaluated while calls are
+    /// waiting
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval
+    }
+    fn number(&self, state: &State, now: Instant) -> u64 {
+        (now.duration_since(state.started).as_nanos() / self.interval.as_nanos()) as u64
+    }
+    /// Record that a call to `Pool::get` found `unused` slots. The call
+    /// counts as pending until the returned `Wait` is dropped.
+    pub(crate) fn start(&self, unused: usize) -> Wait<'_> {
+        let now = Instant::now();
+        let state = &mut *self.state.lock().unwrap();
+        let number = self.number(state, now);
+        let bucket = state.bucket(number);
+        bucket.min_unused = bucket.min_unused.min(unused);
+        state.pending += 1;
+        state.pending_since += now.duration_since(state.started);
+        Wait {
+            autoscaler: self,
+            started: now,
+        }
+    }
+    fn finish(&self, started: Instant) {
+        let now = Instant::now();
+        let state = &mut *self.state.lock().unwrap();
+        state.pending -= 1;
+        state.pending_since -= started.duration_since(state.started);
+        let number = self.number(state, now);
+        let bucket = state.bucket(number);
+        bucket.gets += 1;
+        bucket.waited += now.duration_since(started);
+    }
+    /// Decide about the limit of the pool based on the observations of
+    /// the last window including the calls which are still waiting.
+    /// Returns the new limit if it should be changed. Decisions are made
+    /// at most once per window.
+    pub(crate) fn evaluate(&self, limit: usize) -> Option<usize> {
+        let now = Instant::now();
+        let state = &mut *self.state.lock().unwrap();
+        let current = self.number(state, now);
+        if current + 1 < state.first + BUCKETS as u64 {
+            return None;
+        }
+        let mut gets = state.pending;
+        let mut waited =
+            (now.duration_since(state.started) * state.pending).saturating_sub(state.pending_since);
+        let mut min_unused = usize::MAX;
+        for bucket in &state.buckets {
+            if bucket.number <= current && current - bucket.number < BUCKETS as u64 {
+                gets += bucket.gets;
+                waited += bucket.waited;
+                min_unused = min_unused.min(bucket.min_unused);
+            }
+        }
+        let average_wait = waited.checked_div(gets).unwrap_or_default();
+        let new_limit = if average_wait > self.config.grow_threshold && limit < self.max_size {
+            let new_limit = (limit + self.config.step).min(self.max_size);
+            log::info!(
+                "Growing pool limit from {} to {} after an average wait of {:?}",
+                limit,
+                new_limit,
+                average_wait
+            );
+            new_limit
+        } else if state.pending == 0
+            && min_unused != usize::MAX
+            && min_unused > 0
+            && limit > self.config.min_size
+        {
+            let new_limit = limit
+                .saturating_sub(min_unused.min(self.config.step))
+                .max(self.config.min_size);
+            log::info!(
+                "Shrinking pool limit from {} to {} as {} slots stayed unused",
+                limit,
+                new_limit,
+                min_unused
+            );
+            new_limit
+        } else {
+            return None;
+        };
+        state.first = current + 1;
+        Some(new_limit)
+    }
+}
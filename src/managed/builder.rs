@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use super::hooks::{Hook, Hooks};
+use super::priority::SchedulerConfig;
+use super::AutoscaleConfig;
+use super::{
+    spawn_create, Event, HookError, Manager, Object, Pool, PoolConfig, PoolOptions, SpawnCreate,
+    Timeouts,
+};
+use crate::leak::LeakDetector;
+use crate::observer::Observers;
+use crate::{BuildError, Runtime};
+
+/// Builder for managed pools. A builder is created via `Pool::builder`.
+///
+/// Unlike `Pool::from_config` the configuration is validated when calling
+/// `PoolBuilder::build` so that invalid combinations are reported upfront
+/// rather than on every call to `Pool::get`.
+pub struct PoolBuilder<M: Manager, W: From<Object<M>> = Object<M>> {
+    manager: M,
+    config: PoolConfig,
+    hooks: Hooks<M>,
+    observers: Observers<Event>,
+    leak_threshold: Option<Duration>,
+    poison_on_panic: bool,
+    scheduler: SchedulerConfig,
+    autoscale: Option<AutoscaleConfig>,
+    spawn_create: Option<SpawnCreate<M>>,
+    _wrapper: PhantomData<W>,
+}
+
+impl<M: Manager, W: From<Object<M>>> PoolBuilder<M, W> {
+    pub(crate) fn new(manager: M) -> Self {
+        Self {
+            manager,
+            config: PoolConfig::default(),
+            hooks: Hooks::default(),
+            observers: Observers::default(),
+            leak_threshold: None,
+            poison_on_panic: false,
+            scheduler: SchedulerConfig::default(),
+            autoscale: None,
+            spawn_create: None,
+            _wrapper: PhantomData,
+        }
+    }
+    /// Replace the whole pool configuration
+    pub fn config(mut self, config: PoolConfig) -> Self {
+        self.config = config;
+        self
+    }
+    /// Set the maximum size of the pool
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.config.max_size = max_size;
+        self
+    }
+    /// Set all timeouts at once
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.config.timeouts = timeouts;
+        self
+    }
+    /// Set the timeout when waiting for a slot to become available
+    pub fn wait_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.wait = timeout;
+        self
+    }
+    /// Set the timeout when creating a new object
+    pub fn create_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.create = timeout;
+        self
+    }
+    /// Set the timeout when recycling an object
+    pub fn recycle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.recycle = timeout;
+        self
+    }
+    /// Allow up to `overflow` objects in addition to `max_size` once
+    /// `Pool::get` has been waiting for `after`. If `after` is `None`
+    /// overflow objects are created as soon as the pool is exhausted.
+    /// Overflow objects are destroyed instead of being returned to the
+    /// pool.
+    pub fn overflow(mut self, overflow: usize, after: Option<Duration>) -> Self {
+        self.config.overflow = overflow;
+        self.config.overflow_after = after;
+        self
+    }
+    /// Limit the number of objects which are created at the same time.
+    /// Callers waiting to create an object take an idle object instead
+    /// if one is returned in the meantime.
+    pub fn max_concurrent_creates(mut self, max: usize) -> Self {
+        self.config.max_concurrent_creates = Some(max);
+        self
+    }
+    /// Limit the rate of object creations to `rate` objects per second
+    /// on average allowing bursts of `burst` objects. This requires a
+    /// runtime.
+    pub fn create_rate(mut self, rate: f64, burst: usize) -> Self {
+        self.config.create_rate = Some(rate);
+        self.config.create_burst = burst;
+        self
+    }
+    /// Let every worker thread keep up to `stash_size` idle objects in a
+    /// local stash which `Pool::get` tries before the shared queue. This
+    /// keeps objects on the thread which used them last. Threads whose
+    /// stash and the shared queue are empty take objects from the stashes
+    /// of other threads.
+    pub fn worker_affinity(mut self, stash_size: usize) -> Self {
+        self.config.worker_stash = Some(stash_size);
+        self
+    }
+    /// Set the runtime used for timeouts
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.config.runtime = runtime;
+        self
+    }
+    /// Add a hook which is called after an object has been created. If
+    /// the hook fails the object is discarded and `Pool::get` returns a
+    /// `PoolError::PostCreateHook` error.
+    pub fn post_create<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut M::Type) -> Result<(), HookError<M::Error>> + Send + Sync + 'static,
+    {
+        self.hooks.post_create.push(Box::new(hook) as Hook<M>);
+        self
+    }
+    /// Add a hook which is called after an object has been recycled. If
+    /// the hook fails the object is discarded just like an object whose
+    /// recycling failed.
+    pub fn post_recycle<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut M::Type) -> Result<(), HookError<M::Error>> + Send + Sync + 'static,
+    {
+        self.hooks.post_recycle.push(Box::new(hook) as Hook<M>);
+        self
+    }
+    /// Add an observer which is called for every `Event` of the pool,
+    /// e.g. for collecting metrics. Observers are called synchronously
+    /// by the task causing the event and should return quickly.
+    pub fn observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.observers.push(observer);
+        self
+    }
+    /// Enable leak detection. The caller location of every `Pool::get`
+    /// is recorded and a warning is logged when an object is returned
+    /// after being checked out for longer than `threshold`. Objects which
+    /// are still checked out after `threshold` are reported once when a
+    /// `get` has to wait for an object and, if a runtime is configured,
+    /// periodically while it keeps waiting. The current holders can be
+    /// listed using `Pool::checked_out`.
+    pub fn leak_detection(mut self, threshold: Duration) -> Self {
+        self.leak_threshold = Some(threshold);
+        self
+    }
+    /// Discard objects which are dropped while the thread is panicking
+    /// instead of returning them to the pool. Such objects might be in an
+    /// inconsistent state, e.g. in the middle of a transaction. The number
+    /// of discarded objects is reported by `Status::poisoned`.
+    pub fn poison_on_panic(mut self, value: bool) -> Self {
+        self.poison_on_panic = value;
+        self
+    }
+    /// Set the interval after which a caller waiting for an object gains
+    /// one priority level (see `Pool::get_with_priority`). A caller which
+    /// waited for two intervals with `Priority::Low` is served before
+    /// callers which just started waiting with `Priority::High`. `None`
+    /// disables aging. The default is one second.
+    pub fn priority_aging(mut self, interval: Option<Duration>) -> Self {
+        self.scheduler.aging = interval;
+        self
+    }
+    /// Limit the number of objects every consumer may hold or wait for
+    /// at the same time when using `Pool::get_for`. Consumers without a
+    /// quota are only limited by `max_size`.
+    pub fn consumer_quota(mut self, quota: usize) -> Self {
+        self.scheduler.default_quota = Some(quota);
+        self
+    }
+    /// Set the quota of a single consumer overriding the one set via
+    /// `PoolBuilder::consumer_quota`.
+    pub fn consumer_quota_for(mut self, consumer: impl Into<String>, quota: usize) -> Self {
+        self.scheduler.quotas.insert(consumer.into(), quota);
+        self
+    }
+    /// Enable adaptive sizing of the pool. The limit of the pool is
+    /// adjusted between `AutoscaleConfig::min_size` and `max_size` based
+    /// on the observed wait times and unused slots. The current limit is
+    /// returned by `Pool::limit`.
+    pub fn autoscale(mut self, config: AutoscaleConfig) -> Self {
+        self.autoscale = Some(config);
+        self
+    }
+    /// Use a different wrapper type for the objects returned by the pool
+    pub fn wrapper<W2: From<Object<M>>>(self) -> PoolBuilder<M, W2> {
+        PoolBuilder {
+            manager: self.manager,
+            config: self.config,
+            hooks: self.hooks,
+            observers: self.observers,
+            leak_threshold: self.leak_threshold,
+            poison_on_panic: self.poison_on_panic,
+            scheduler: self.scheduler,
+            autoscale: self.autoscale,
+            spawn_create: self.spawn_create,
+            _wrapper: PhantomData,
+        }
+    }
+    /// Validate the configuration and create the pool
+    pub fn build(self) -> Result<Pool<M, W>, BuildError> {
+        validate(&self.config)?;
+        if self.spawn_create.is_some() && matches!(self.config.runtime, Runtime::None) {
+            return Err(BuildError::NoRuntimeSpecified("race_creates"));
+        }
+        Ok(Pool::from_parts(
+            self.manager,
+            self.config,
+            PoolOptions {
+                hooks: self.hooks,
+                observers: self.observers,
+                leak_detector: self.leak_threshold.map(LeakDetector::new),
+                poison_on_panic: self.poison_on_panic,
+                scheduler: self.scheduler,
+                autoscale: self.autoscale,
+                spawn_create: self.spawn_create,
+                idle_notify: None,
+            },
+        ))
+    }
+}
+
+impl<M, W> PoolBuilder<M, W>
+where
+    M: Manager + Send + Sync + 'static,
+    M::Type: Send + 'static,
+    M::Error: Send + 'static,
+    W: From<Object<M>>,
+{
+    /// Create objects in separate tasks so that a caller waiting for a
+    /// new object takes an object which is returned to the pool in the
+    /// meantime. The object which is still being created is added to
+    /// the pool once it is ready. This reduces the wait time when
+    /// creating objects is slow and requires a runtime.
+    pub fn race_creates(mut self, value: bool) -> Self {
+        self.spawn_create = if value { Some(spawn_create::<M>) } else { None };
+        self
+    }
+}
+
+fn validate(config: &PoolConfig) -> Result<(), BuildError> {
+    if config.max_size == 0 {
+        return Err(BuildError::ZeroMaxSize);
+    }
+    if config.max_concurrent_creates == Some(0) {
+        return Err(BuildError::ZeroMaxConcurrentCreates);
+    }
+    if matches!(config.runtime, Runtime::None) {
+        let timeouts = &config.timeouts;
+        // A wait timeout of zero is handled without a runtime.
+        if matches!(timeouts.wait, Some(t) if t.as_nanos() > 0) {
+            return Err(BuildError::NoRuntimeSpecified("wait"));
+        }
+        if timeouts.create.is_some() {
+            return Err(BuildError::NoRuntimeSpecified("create"));
+        }
+        if timeouts.recycle.is_some() {
+            return Err(BuildError::NoRuntimeSpecified("recycle"));
+        }
+        if config.overflow > 0 && matches!(config.overflow_after, Some(t) if t.as_nanos() > 0) {
+            return Err(BuildError::NoRuntimeSpecified("overflow_after"));
+        }
+        if matches!(config.create_rate, Some(rate) if rate > 0.0) {
+            return Err(BuildError::NoRuntimeSpecified("create_rate"));
+        }
+    }
+    Ok(())
+}
@@ -14,6 +14,34 @@ pub struct PoolConfig {
     /// Runtime
     #[cfg_attr(feature = "config", serde(skip))]
     pub runtime: Runtime,
+    /// Number of objects which may be created in addition to `max_size`
+    /// once `Pool::get` has been waiting for `overflow_after`. Overflow
+    /// objects are destroyed instead of being returned to the pool.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub overflow: usize,
+    /// Time `Pool::get` waits for a slot before creating an overflow
+    /// object. If `None` overflow objects are created as soon as the pool
+    /// is exhausted.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub overflow_after: Option<Duration>,
+    /// Maximum number of objects which are created at the same time. If
+    /// `None` up to `max_size` objects are created at once.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub max_concurrent_creates: Option<usize>,
+    /// Maximum average number of objects created per second. If `None`
+    /// the rate of object creations is not limited.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub create_rate: Option<f64>,
+    /// Number of objects which may be created in a burst before
+    /// `create_rate` applies. Values below one are treated as one.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub create_burst: usize,
+    /// Number of idle objects every worker thread keeps in a local stash
+    /// which is tried before the shared queue. Objects returned to the
+    /// pool stay on the thread that used them last. If `None` all idle
+    /// objects are kept in the shared queue.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub worker_stash: Option<usize>,
 }
 
 impl PoolConfig {
@@ -23,6 +51,12 @@ impl PoolConfig {
             max_size,
             timeouts: Timeouts::default(),
             runtime: Runtime::default(),
+            overflow: 0,
+            overflow_after: None,
+            max_concurrent_creates: None,
+            create_rate: None,
+            create_burst: 1,
+            worker_stash: None,
         }
     }
 }
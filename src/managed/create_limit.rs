@@ -0,0 +1,98 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::PoolConfig;
+use crate::runtime::{Runtime, TimeoutError};
+
+/// Limits the number of objects which are created at the same time and
+/// the rate at which they are created.
+pub(crate) struct CreateLimiter {
+    concurrency: Option<Arc<Semaphore>>,
+    bucket: Option<TokenBucket>,
+}
+
+/// Permission to create an object. The slot is given back when this is
+/// dropped.
+pub(crate) struct CreateSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl CreateLimiter {
+    /// Create a limiter for the given config or `None` if object
+    /// creations are not limited.
+    pub(crate) fn new(config: &PoolConfig) -> Option<Self> {
+        let concurrency = config
+            .max_concurrent_creates
+            .map(|n| Arc::new(Semaphore::new(n.max(1))));
+        let bucket = config
+            .create_rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| TokenBucket::new(rate, config.create_burst.max(1)));
+        if concurrency.is_none() && bucket.is_none() {
+            return None;
+        }
+        Some(Self {
+            concurrency,
+            bucket,
+        })
+    }
+    /// Wait for permission to create an object
+    pub(crate) async fn acquire(&self, runtime: &Runtime) -> Result<CreateSlot, TimeoutError> {
+        let permit = match &self.concurrency {
+            // The semaphore is never closed.
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+            None => None,
+        };
+        if let Some(bucket) = &self.bucket {
+            while let Err(wait) = bucket.try_take() {
+                runtime.sleep(wait).await?;
+            }
+        }
+        Ok(CreateSlot { _permit: permit })
+    }
+}
+
+struct Tokens {
+    available: f64,
+    updated: Instant,
+}
+
+/// A token bucket which is refilled with `rate` tokens per second and
+/// holds at most `burst` tokens.
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: Mutex<Tokens>,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: usize) -> Self {
+        Self {
+            rate,
+            burst: burst as f64,
+            tokens: Mutex::new(Tokens {
+                available: burst as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+    /// Take a token or return the time until the next token is
+    /// available.
+    fn try_take(&self) -> Result<(), Duration> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(tokens.updated).as_secs_f64() * self.rate;
+        tokens.available = (tokens.available + refill).min(self.burst);
+        tokens.updated = now;
+        if tokens.available >= 1.0 {
+            tokens.available -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - tokens.available) / self.rate,
+            ))
+        }
+    }
+}
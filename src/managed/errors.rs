@@ -29,9 +29,38 @@ where
 
 impl<E> std::error::Error for RecycleError<E> where E: std::error::Error {}
 
+/// This error is returned by hooks registered via the `PoolBuilder`
+#[derive(Debug)]
+pub enum HookError<E> {
+    /// The hook failed for some other reason
+    Message(String),
+    /// The error was caused by the backend
+    Backend(E),
+}
+
+impl<E> From<E> for HookError<E> {
+    fn from(e: E) -> Self {
+        Self::Backend(e)
+    }
+}
+
+impl<E> fmt::Display for HookError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message(msg) => write!(f, "An error occured while executing a hook: {}", msg),
+            Self::Backend(e) => write!(f, "An error occured while executing a hook: {}", e),
+        }
+    }
+}
+
+impl<E> std::error::Error for HookError<E> where E: std::error::Error {}
+
 /// When `Pool::get` returns a timeout error this enum can be used
 /// to figure out which step caused the timeout.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TimeoutType {
     /// The timeout happened while waiting for a slot to become available
     Wait,
@@ -52,6 +81,18 @@ pub enum PoolError<E> {
     Closed,
     /// No runtime specified
     NoRuntimeSpecified,
+    /// A `post_create` hook reported an error
+    PostCreateHook(HookError<E>),
+    /// The consumer passed to `Pool::get_for` already holds or waits for
+    /// as many objects as its quota allows
+    QuotaExceeded,
+    /// The task creating the object panicked or was cancelled. See
+    /// `PoolBuilder::race_creates`.
+    CreateAborted,
+    /// A blocking get method was called from within an async runtime
+    BlockingInAsyncContext,
+    /// `PoolSet::get` was called on a set without any pools
+    NoPools,
 }
 
 impl<E> From<E> for PoolError<E> {
@@ -77,6 +118,14 @@ where
             Self::Backend(e) => write!(f, "An error occured while creating a new object: {}", e),
             Self::Closed => write!(f, "The pool has been closed."),
             Self::NoRuntimeSpecified => write!(f, "No runtime specified."),
+            Self::PostCreateHook(e) => write!(f, "{}", e),
+            Self::QuotaExceeded => write!(f, "The quota of the consumer has been exceeded."),
+            Self::CreateAborted => write!(f, "The task creating a new object was aborted."),
+            Self::BlockingInAsyncContext => write!(
+                f,
+                "Blocking get methods must not be called from within an async runtime."
+            ),
+            Self::NoPools => write!(f, "The pool set contains no pools."),
         }
     }
 }
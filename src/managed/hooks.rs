@@ -0,0 +1,33 @@
+use super::{HookError, Manager};
+
+/// A hook which is called with the pooled object. Hooks can be registered
+/// using the `PoolBuilder::post_create` and `PoolBuilder::post_recycle`
+/// methods.
+pub type Hook<M> = Box<
+    dyn Fn(&mut <M as Manager>::Type) -> Result<(), HookError<<M as Manager>::Error>> + Send + Sync,
+>;
+
+/// Hooks registered with a pool
+pub(crate) struct Hooks<M: Manager> {
+    pub post_create: Vec<Hook<M>>,
+    pub post_recycle: Vec<Hook<M>>,
+}
+
+impl<M: Manager> Hooks<M> {
+    /// Run all hooks of the given list stopping at the first error
+    pub fn run(hooks: &[Hook<M>], obj: &mut M::Type) -> Result<(), HookError<M::Error>> {
+        for hook in hooks {
+            hook(obj)?;
+        }
+        Ok(())
+    }
+}
+
+impl<M: Manager> Default for Hooks<M> {
+    fn default() -> Self {
+        Self {
+            post_create: Vec::new(),
+            post_recycle: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use super::{
+    apply_timeout, Manager, Object, Pool, PoolConfig, PoolError, PoolOptions, RecycleResult,
+    Status, TimeoutType, Timeouts,
+};
+use crate::Runtime;
+
+/// This trait is used to `create` new objects or `recycle` existing ones
+/// for a given key.
+#[async_trait]
+pub trait KeyedManager<K: Send + Sync>: Send + Sync {
+    /// Type that the manager creates and recycles.
+    type Type: Send;
+    /// The error that the manager can return when creating and recycling
+    /// objects.
+    type Error;
+    /// Create a new instance of `Type` for the given key
+    async fn create(&self, key: &K) -> Result<Self::Type, Self::Error>;
+    /// Try to recycle an instance of `Type` which was created for the
+    /// given key returning an `Error` if the object could not be recycled.
+    async fn recycle(&self, key: &K, obj: &mut Self::Type) -> RecycleResult<Self::Error>;
+    /// Detach an instance of `Type` from this manager. See
+    /// `Manager::detach` for details.
+    fn detach(&self, _key: &K, _obj: &mut Self::Type) {}
+}
+
+/// Keyed pool configuration
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize))]
+pub struct KeyedPoolConfig {
+    /// Maximum number of objects across all keys
+    pub max_size: usize,
+    /// Maximum number of objects per key
+    pub max_size_per_key: usize,
+    /// Timeouts
+    #[cfg_attr(feature = "config", serde(default))]
+    pub timeouts: Timeouts,
+    /// Runtime
+    #[cfg_attr(feature = "config", serde(skip))]
+    pub runtime: Runtime,
+}
+
+impl KeyedPoolConfig {
+    /// Create keyed pool config without any timeouts
+    pub fn new(max_size: usize, max_size_per_key: usize) -> Self {
+        Self {
+            max_size,
+            max_size_per_key,
+            timeouts: Timeouts::default(),
+            runtime: Runtime::default(),
+        }
+    }
+    fn pool_config(&self) -> PoolConfig {
+        PoolConfig {
+            max_size: self.max_size_per_key,
+            timeouts: self.timeouts.clone(),
+            runtime: self.runtime.clone(),
+            overflow: 0,
+            overflow_after: None,
+            max_concurrent_creates: None,
+            create_rate: None,
+            create_burst: 1,
+            worker_stash: None,
+        }
+    }
+}
+
+/// An object created by a `KeyedManager`. It occupies one slot of the
+/// global `max_size` of its `KeyedPool` until it is dropped.
+pub struct Slot<T> {
+    obj: T,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<T> Slot<T> {
+    /// Unwrap the object releasing its slot
+    pub fn into_inner(this: Self) -> T {
+        this.obj
+    }
+}
+
+impl<T> Deref for Slot<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.obj
+    }
+}
+
+impl<T> DerefMut for Slot<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.obj
+    }
+}
+
+/// A global slot acquired by `KeyedPool::timeout_get` while waiting.
+/// It is used by the next object created for the key or released when
+/// the call returns.
+type Reservation = Arc<Mutex<Option<OwnedSemaphorePermit>>>;
+
+/// The `Manager` used by the sub-pools of a `KeyedPool`. It binds a
+/// `KeyedManager` to a single key.
+pub struct KeyManager<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    key: K,
+    manager: Arc<M>,
+    semaphore: Arc<Semaphore>,
+    /// Notified whenever an object of any key is returned to its sub-pool
+    idle: Arc<Notify>,
+    pools: Weak<Mutex<HashMap<K, Entry<K, M>>>>,
+    reservations: Mutex<Vec<Reservation>>,
+}
+
+impl<K, M> KeyManager<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    /// Get the key this manager creates objects for
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    /// Try to free a global slot by removing an idle object. Objects of
+    /// other keys are preferred over objects of this key. Returns true
+    /// if an object was removed.
+    fn evict_one(&self) -> bool {
+        let pools = match self.pools.upgrade() {
+            Some(pools) => pools,
+            None => return false,
+        };
+        let obj = {
+            let pools = pools.lock().unwrap();
+            pools
+                .iter()
+                .filter(|(key, _)| **key != self.key)
+                .chain(pools.get_key_value(&self.key))
+                .find_map(|(_, entry)| entry.pool.inner.pop_idle())
+        };
+        // The slot is released when the object is dropped which must
+        // happen without holding the lock.
+        obj.is_some()
+    }
+    /// Make `permit` available to the next creation of an object for
+    /// this key. The permit is released when the returned guard is
+    /// dropped unless it has been used.
+    fn reserve(&self, permit: OwnedSemaphorePermit) -> Reserved<'_, K, M> {
+        let reservation = Arc::new(Mutex::new(Some(permit)));
+        self.reservations.lock().unwrap().push(reservation.clone());
+        Reserved {
+            manager: self,
+            reservation,
+        }
+    }
+    /// Take a global slot reserved by any caller waiting for this key
+    fn take_reservation(&self) -> Option<OwnedSemaphorePermit> {
+        let reservations = self.reservations.lock().unwrap();
+        reservations
+            .iter()
+            .find_map(|reservation| reservation.lock().unwrap().take())
+    }
+    /// Acquire a global slot. Idle objects keep their slots, so whenever
+    /// the global limit is reached an idle object is evicted. If there
+    /// is none the slot is awaited until an object is returned to any
+    /// of the sub-pools which can then be evicted.
+    async fn acquire_slot(&self) -> OwnedSemaphorePermit {
+        loop {
+            let returned = self.idle.notified();
+            tokio::pin!(returned);
+            returned.as_mut().enable();
+            if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+                return permit;
+            }
+            if self.evict_one() {
+                continue;
+            }
+            let mut acquire = Box::pin(self.semaphore.clone().acquire_owned());
+            let permit = std::future::poll_fn(|cx| {
+                if let Poll::Ready(permit) = acquire.as_mut().poll(cx) {
+                    return Poll::Ready(Some(permit));
+                }
+                returned.as_mut().poll(cx).map(|()| None)
+            })
+            .await;
+            if let Some(permit) = permit {
+                // The global semaphore is never closed.
+                return permit.unwrap();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<K, M> Manager for KeyManager<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    type Type = Slot<M::Type>;
+    type Error = M::Error;
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        // Callers reserve a slot before waiting for the sub-pool. A
+        // creation without one, e.g. because the idle object a caller
+        // expected was taken by another one, waits for a slot here.
+        let permit = match self.take_reservation() {
+            Some(permit) => permit,
+            None => self.acquire_slot().await,
+        };
+        Ok(Slot {
+            obj: self.manager.create(&self.key).await?,
+            _permit: permit,
+        })
+    }
+    async fn recycle(&self, obj: &mut Self::Type) -> RecycleResult<Self::Error> {
+        self.manager.recycle(&self.key, &mut obj.obj).await
+    }
+    fn detach(&self, obj: &mut Self::Type) {
+        self.manager.detach(&self.key, &mut obj.obj)
+    }
+}
+
+/// Releases a reserved slot which has not been used
+struct Reserved<'a, K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    manager: &'a KeyManager<K, M>,
+    reservation: Reservation,
+}
+
+impl<K, M> Drop for Reserved<'_, K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    fn drop(&mut self) {
+        self.reservation.lock().unwrap().take();
+        self.manager
+            .reservations
+            .lock()
+            .unwrap()
+            .retain(|reservation| !Arc::ptr_eq(reservation, &self.reservation));
+    }
+}
+
+struct Entry<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    pool: Pool<KeyManager<K, M>>,
+    last_used: Instant,
+}
+
+/// A pool which maintains one sub-pool per key. Every key has its own
+/// idle queue and `max_size_per_key` while `max_size` is enforced across
+/// all keys. When the global limit is reached idle objects are removed
+/// to make room for new ones, preferring objects of other keys. If there
+/// are no idle objects the caller waits until an object is returned.
+/// This wait is part of the `wait` timeout.
+///
+/// This struct can be cloned and transferred across thread boundaries
+/// and uses reference counting for its internal state.
+pub struct KeyedPool<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    manager: Arc<M>,
+    semaphore: Arc<Semaphore>,
+    idle: Arc<Notify>,
+    pools: Arc<Mutex<HashMap<K, Entry<K, M>>>>,
+    closed: Arc<AtomicBool>,
+    config: KeyedPoolConfig,
+}
+
+impl<K, M> Clone for KeyedPool<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            manager: self.manager.clone(),
+            semaphore: self.semaphore.clone(),
+            idle: self.idle.clone(),
+            pools: self.pools.clone(),
+            closed: self.closed.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<K, M> KeyedPool<K, M>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    M: KeyedManager<K>,
+{
+    /// Create new keyed pool with a given `manager`, global `max_size`
+    /// and `max_size_per_key`.
+    pub fn new(manager: M, max_size: usize, max_size_per_key: usize) -> Self {
+        Self::from_config(manager, KeyedPoolConfig::new(max_size, max_size_per_key))
+    }
+    /// Create new keyed pool with a given `manager` and `config`.
+    pub fn from_config(manager: M, config: KeyedPoolConfig) -> Self {
+        Self {
+            manager: Arc::new(manager),
+            semaphore: Arc::new(Semaphore::new(config.max_size)),
+            idle: Arc::new(Notify::new()),
+            pools: Arc::new(Mutex::new(HashMap::new())),
+            closed: Arc::new(AtomicBool::new(false)),
+            config,
+        }
+    }
+    /// Retrieve object for the given key or wait for one to become
+    /// available.
+    pub async fn get(&self, key: &K) -> Result<Object<KeyManager<K, M>>, PoolError<M::Error>> {
+        self.timeout_get(key, &self.config.timeouts).await
+    }
+    /// Retrieve object for the given key using a different timeout config
+    /// than the one configured.
+    ///
+    /// If the sub-pool of the key has no idle object but room for a new
+    /// one a global slot is acquired first. The time spent waiting for
+    /// it counts against `timeouts.wait`.
+    pub async fn timeout_get(
+        &self,
+        key: &K,
+        timeouts: &Timeouts,
+    ) -> Result<Object<KeyManager<K, M>>, PoolError<M::Error>> {
+        if self.is_closed() {
+            return Err(PoolError::Closed);
+        }
+        let pool = self.pool(key);
+        let status = pool.status();
+        if status.available > 0 || status.size >= status.max_size {
+            return pool.timeout_get(timeouts).await;
+        }
+        let started = Instant::now();
+        let manager = pool.manager();
+        let permit = apply_timeout(
+            &self.config.runtime,
+            TimeoutType::Wait,
+            timeouts.wait,
+            async { Ok::<_, PoolError<M::Error>>(manager.acquire_slot().await) },
+        )
+        .await?;
+        let _reserved = manager.reserve(permit);
+        let mut timeouts = timeouts.clone();
+        timeouts.wait = timeouts
+            .wait
+            .map(|wait| wait.saturating_sub(started.elapsed()));
+        pool.timeout_get(&timeouts).await
+    }
+    /// Get the sub-pool of the given key creating it if needed.
+    fn pool(&self, key: &K) -> Pool<KeyManager<K, M>> {
+        let mut pools = self.pools.lock().unwrap();
+        let entry = pools.entry(key.clone()).or_insert_with(|| Entry {
+            pool: Pool::from_parts(
+                KeyManager {
+                    key: key.clone(),
+                    manager: self.manager.clone(),
+                    semaphore: self.semaphore.clone(),
+                    idle: self.idle.clone(),
+                    pools: Arc::downgrade(&self.pools),
+                    reservations: Mutex::new(Vec::new()),
+                },
+                self.config.pool_config(),
+                PoolOptions {
+                    idle_notify: Some(self.idle.clone()),
+                    ..PoolOptions::default()
+                },
+            ),
+            last_used: Instant::now(),
+        });
+        entry.last_used = Instant::now();
+        entry.pool.clone()
+    }
+    /// Remove the sub-pools of all keys which have no objects checked out
+    /// and have not been used for at least `max_idle`. Their idle objects
+    /// are dropped. Returns the number of evicted keys.
+    pub fn evict_idle(&self, max_idle: Duration) -> usize {
+        let evicted = {
+            let mut pools = self.pools.lock().unwrap();
+            let keys = pools
+                .iter()
+                .filter(|(_, entry)| {
+                    let status = entry.pool.status();
+                    status.available >= 0
+                        && status.available as usize == status.size
+                        && entry.last_used.elapsed() >= max_idle
+                })
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            keys.iter()
+                .filter_map(|key| pools.remove(key))
+                .collect::<Vec<_>>()
+        };
+        // Drop the sub-pools without holding the lock.
+        evicted.len()
+    }
+    /// Close the pool and all of its sub-pools
+    ///
+    /// All current and future tasks waiting for objects return
+    /// `Err(PoolError::Closed)` immediately.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        for entry in self.pools.lock().unwrap().values() {
+            entry.pool.close();
+        }
+    }
+    /// Retrieve aggregated status of all sub-pools
+    pub fn status(&self) -> Status {
+        let pools = self.pools.lock().unwrap();
+        let status = pools
+            .values()
+            .map(|entry| entry.pool.status())
+            .sum::<Status>();
+        Status {
+            max_size: self.config.max_size,
+            ..status
+        }
+    }
+    /// Retrieve status of the sub-pool of the given key
+    pub fn key_status(&self, key: &K) -> Option<Status> {
+        let pools = self.pools.lock().unwrap();
+        pools.get(key).map(|entry| entry.pool.status())
+    }
+    /// Returns true if the pool has been closed
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+    /// Get manager of the pool
+    pub fn manager(&self) -> &M {
+        &self.manager
+    }
+}
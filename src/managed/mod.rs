@@ -47,22 +47,95 @@
 //! For a more complete example please see
 //! [`deadpool-postgres`](https://crates.io/crates/deadpool-postgres)
 
-use std::ops::{Deref, DerefMut};
+use std::convert::TryFrom;
+use std::iter::Sum;
+use std::ops::{Add, Deref, DerefMut};
+use std::panic::Location;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
-use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::sync::{Arc, RwLock, Weak};
+use std::task::Poll;
+use std::time::{Duration, Instant};
 use std::{future::Future, marker::PhantomData};
 
 use async_trait::async_trait;
-use tokio::sync::{Semaphore, TryAcquireError};
+use tokio::sync::{oneshot, Notify};
 
+mod autoscale;
+pub use self::autoscale::AutoscaleConfig;
+use self::autoscale::Autoscaler;
+mod builder;
+mod create_limit;
+pub use self::builder::PoolBuilder;
+use self::create_limit::{CreateLimiter, CreateSlot};
 mod config;
 pub use self::config::{PoolConfig, Timeouts};
 mod errors;
-pub use errors::{PoolError, RecycleError, TimeoutType};
+pub use errors::{HookError, PoolError, RecycleError, TimeoutType};
+mod hooks;
+pub use self::hooks::Hook;
+use self::hooks::Hooks;
+mod keyed;
+mod observer;
+pub use self::observer::Event;
+mod priority;
+pub use self::priority::Priority;
+use self::priority::{AcquireError, Consumer, PrioritySemaphore, SchedulerConfig};
 
-use crate::runtime::{Runtime, TimeoutError};
-pub use crate::Status;
+pub use self::keyed::{KeyManager, KeyedManager, KeyedPool, KeyedPoolConfig, Slot};
+mod set;
+pub use self::set::{PoolSet, PoolSetConfig, Strategy};
+mod shared;
+pub use self::shared::{SharedObject, SharedPool, SharedPoolConfig};
+
+use crate::leak::{self, LeakDetector};
+use crate::observer::Observers;
+use crate::queue::IdleQueue;
+use crate::runtime::{block_in_place, Runtime, RuntimeContext, TimeoutError};
+pub use crate::{BuildError, CheckedOut, Metrics, Status, Waiting};
+
+impl Add for Waiting {
+    type Output = Waiting;
+    fn add(self, rhs: Waiting) -> Waiting {
+        Waiting {
+            low: self.low + rhs.low,
+            normal: self.normal + rhs.normal,
+            high: self.high + rhs.high,
+        }
+    }
+}
+
+/// Adds up the counters of two pools. This is used by `KeyedPool` and
+/// `PoolSet` to aggregate the status of their pools.
+impl Add for Status {
+    type Output = Status;
+    fn add(self, rhs: Status) -> Status {
+        Status {
+            max_size: self.max_size + rhs.max_size,
+            size: self.size + rhs.size,
+            available: self.available + rhs.available,
+            poisoned: self.poisoned + rhs.poisoned,
+            used_weight: self.used_weight + rhs.used_weight,
+            waiting: self.waiting + rhs.waiting,
+            overflow: self.overflow + rhs.overflow,
+        }
+    }
+}
+
+impl Sum for Status {
+    fn sum<I: Iterator<Item = Status>>(iter: I) -> Status {
+        let empty = Status {
+            max_size: 0,
+            size: 0,
+            available: 0,
+            poisoned: 0,
+            used_weight: 0,
+            waiting: Waiting::default(),
+            overflow: 0,
+        };
+        iter.fold(empty, Add::add)
+    }
+}
 
 /// Result type for the recycle function
 pub type RecycleResult<E> = Result<(), RecycleError<E>>;
@@ -106,6 +179,19 @@ pub struct Object<M: Manager> {
     obj: Option<M::Type>,
     state: ObjectState,
     pool: Weak<PoolInner<M>>,
+    /// Id of the checkout if leak detection is enabled
+    checkout: Option<u64>,
+    /// Discard the object instead of returning it to the pool
+    poisoned: bool,
+    /// Generation of the pool the object was created in
+    generation: usize,
+    metrics: Metrics,
+    /// Number of semaphore permits held by this object
+    weight: u32,
+    /// The consumer the permits were acquired for
+    consumer: Option<Consumer>,
+    /// The object exceeds `max_size` and is destroyed when returned
+    overflow: bool,
 }
 
 impl<M: Manager> Object<M> {
@@ -114,35 +200,83 @@ impl<M: Manager> Object<M> {
     pub fn take(mut this: Self) -> M::Type {
         this.state = ObjectState::Taken;
         if let Some(pool) = this.pool.upgrade() {
-            pool.manager.detach(&mut this);
+            pool.manager().detach(&mut this);
+            pool.checkin(this.checkout.take());
         }
         this.obj.take().unwrap()
     }
+    /// Mark this object as poisoned. Poisoned objects are discarded
+    /// instead of being returned to the pool. This is useful to protect
+    /// sections of code which leave the object in an inconsistent state
+    /// if the future using the object is dropped before completion.
+    pub fn poison(this: &mut Self) {
+        this.poisoned = true;
+    }
+    /// Clear the poisoned state of this object.
+    pub fn clear_poison(this: &mut Self) {
+        this.poisoned = false;
+    }
+    /// Returns true if this object is poisoned.
+    pub fn is_poisoned(this: &Self) -> bool {
+        this.poisoned
+    }
+    /// Get the metrics of this object
+    pub fn metrics(this: &Self) -> &Metrics {
+        &this.metrics
+    }
 }
 
 impl<M: Manager> Drop for Object<M> {
     fn drop(&mut self) {
         if let Some(pool) = self.pool.upgrade() {
+            pool.checkin(self.checkout.take());
+            if matches!(self.state, ObjectState::Ready) {
+                pool.observers.notify(Event::Return);
+            }
+            if matches!(self.state, ObjectState::Ready | ObjectState::Taken) {
+                pool.used_weight
+                    .fetch_sub(self.weight as usize, Ordering::Relaxed);
+            }
             match self.state {
                 ObjectState::Waiting => {
                     pool.available.fetch_add(1, Ordering::Relaxed);
                 }
                 ObjectState::Receiving => {
                     pool.available.fetch_add(1, Ordering::Relaxed);
-                    pool.semaphore.add_permits(1);
+                    pool.release(self.weight, &self.consumer, self.overflow);
                 }
                 ObjectState::Creating | ObjectState::Taken => {
                     pool.size.fetch_sub(1, Ordering::Relaxed);
-                    pool.semaphore.add_permits(1);
+                    pool.release(self.weight, &self.consumer, self.overflow);
+                }
+                ObjectState::Recycling | ObjectState::Ready
+                    if self.poisoned || (pool.poison_on_panic && std::thread::panicking()) =>
+                {
+                    // The object might be in an inconsistent state and
+                    // is discarded.
+                    pool.poisoned.fetch_add(1, Ordering::Relaxed);
+                    pool.size.fetch_sub(1, Ordering::Relaxed);
+                    pool.release(self.weight, &self.consumer, self.overflow);
+                }
+                ObjectState::Recycling | ObjectState::Ready
+                    if self.overflow
+                        || self.generation != pool.generation.load(Ordering::Relaxed) =>
+                {
+                    // The object is an overflow object or the pool has
+                    // been invalidated since this object was created.
+                    pool.size.fetch_sub(1, Ordering::Relaxed);
+                    pool.release(self.weight, &self.consumer, self.overflow);
                 }
                 ObjectState::Recycling | ObjectState::Ready => {
                     pool.available.fetch_add(1, Ordering::Relaxed);
-                    let obj = self.obj.take().unwrap();
-                    {
-                        let mut queue = pool.queue.lock().unwrap();
-                        queue.push(obj);
-                    }
-                    pool.semaphore.add_permits(1);
+                    let obj = ObjectInner {
+                        obj: self.obj.take().unwrap(),
+                        generation: self.generation,
+                        metrics: self.metrics,
+                    };
+                    pool.queue.push(obj);
+                    pool.notify_returned();
+                    pool.release(self.weight, &self.consumer, self.overflow);
                     // The pool might have been closed in the mean time.
                     // Hand over control to the `_cleanup` method which
                     // takes care of this.
@@ -183,16 +317,201 @@ impl<M: Manager> AsMut<M::Type> for Object<M> {
     }
 }
 
+/// A reference to the manager of a pool returned by `Pool::manager`.
+/// It keeps the manager alive even if it is replaced using
+/// `Pool::set_manager` while the reference is held.
+pub struct ManagerRef<'a, M> {
+    manager: Arc<M>,
+    _pool: PhantomData<&'a M>,
+}
+
+impl<M> Deref for ManagerRef<'_, M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.manager
+    }
+}
+
+impl<M> AsRef<M> for ManagerRef<'_, M> {
+    fn as_ref(&self) -> &M {
+        self
+    }
+}
+
+/// An idle object stored in the queue of the pool
+struct ObjectInner<M: Manager> {
+    obj: M::Type,
+    /// Generation of the pool the object was created in
+    generation: usize,
+    metrics: Metrics,
+}
+
 struct PoolInner<M: Manager> {
-    manager: Box<M>,
-    queue: std::sync::Mutex<Vec<M::Type>>,
+    /// The manager is kept in an `Arc` so it can be replaced using
+    /// `Pool::set_manager` while objects are being created.
+    manager: RwLock<Arc<M>>,
+    queue: IdleQueue<ObjectInner<M>>,
     size: AtomicUsize,
     /// The number of available objects in the pool. If there are no
     /// objects in the pool this number can become negative and stores the
     /// number of futures waiting for an object.
     available: AtomicIsize,
-    semaphore: Semaphore,
+    semaphore: PrioritySemaphore,
     config: PoolConfig,
+    hooks: Hooks<M>,
+    leak_detector: Option<LeakDetector>,
+    /// Discard objects which are dropped while the thread is panicking
+    poison_on_panic: bool,
+    /// The number of discarded poisoned objects
+    poisoned: AtomicUsize,
+    /// Incremented by `Pool::invalidate_all`. Objects of older generations
+    /// are discarded instead of being reused.
+    generation: AtomicUsize,
+    /// The sum of the weights of all checked out objects
+    used_weight: AtomicUsize,
+    /// Adjusts the limit of the pool if autoscaling is enabled
+    autoscaler: Option<Autoscaler>,
+    /// Limits concurrent object creations and their rate if configured
+    create_limiter: Option<CreateLimiter>,
+    /// Notified whenever an object is returned to the queue
+    returned: Notify,
+    /// Spawns object creations as separate tasks if creations race
+    /// against returned objects (see `PoolBuilder::race_creates`)
+    spawn_create: Option<SpawnCreate<M>>,
+    /// Notified together with `returned`. This is used by `KeyedPool` to
+    /// wake up creations which wait for a global slot.
+    idle_notify: Option<Arc<Notify>>,
+    /// The context the pool was created in. Used by `Pool::blocking_get`.
+    context: RuntimeContext,
+    observers: Observers<Event>,
+}
+
+/// Settings which can only be changed via the `PoolBuilder`
+struct PoolOptions<M: Manager> {
+    hooks: Hooks<M>,
+    observers: Observers<Event>,
+    leak_detector: Option<LeakDetector>,
+    poison_on_panic: bool,
+    scheduler: SchedulerConfig,
+    autoscale: Option<AutoscaleConfig>,
+    spawn_create: Option<SpawnCreate<M>>,
+    idle_notify: Option<Arc<Notify>>,
+}
+
+impl<M: Manager> Default for PoolOptions<M> {
+    fn default() -> Self {
+        Self {
+            hooks: Hooks::default(),
+            observers: Observers::default(),
+            leak_detector: None,
+            poison_on_panic: false,
+            scheduler: SchedulerConfig::default(),
+            autoscale: None,
+            spawn_create: None,
+            idle_notify: None,
+        }
+    }
+}
+
+type CreateResult<M> = Result<<M as Manager>::Type, PoolError<<M as Manager>::Error>>;
+
+/// Receives the result of a spawned object creation. `None` is sent if
+/// the creation panicked.
+type CreateReceiver<M> = oneshot::Receiver<Option<CreateResult<M>>>;
+
+/// Spawns `Manager::create` for an object of the given generation as a
+/// separate task using the given create timeout. Returns `None` if no
+/// runtime is specified.
+type SpawnCreate<M> = fn(
+    &Arc<PoolInner<M>>,
+    Arc<M>,
+    Option<CreateSlot>,
+    usize,
+    Option<Duration>,
+) -> Option<CreateReceiver<M>>;
+
+/// Create an object in a separate task. If the caller stopped waiting
+/// for the object in the meantime it is added to the pool instead.
+fn spawn_create<M>(
+    pool: &Arc<PoolInner<M>>,
+    manager: Arc<M>,
+    slot: Option<CreateSlot>,
+    generation: usize,
+    timeout: Option<Duration>,
+) -> Option<CreateReceiver<M>>
+where
+    M: Manager + Send + Sync + 'static,
+    M::Type: Send + 'static,
+    M::Error: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let mut task = CreateTask {
+        tx: Some(tx),
+        pool: Arc::downgrade(pool),
+        generation,
+    };
+    let runtime = pool.config.runtime.clone();
+    let spawned = pool.config.runtime.spawn(async move {
+        let result = apply_timeout(&runtime, TimeoutType::Create, timeout, manager.create()).await;
+        drop(slot);
+        task.finish(Some(result));
+    });
+    if spawned {
+        Some(rx)
+    } else {
+        None
+    }
+}
+
+/// The sending half of a spawned object creation
+struct CreateTask<M: Manager> {
+    tx: Option<oneshot::Sender<Option<CreateResult<M>>>>,
+    pool: Weak<PoolInner<M>>,
+    generation: usize,
+}
+
+impl<M: Manager> CreateTask<M> {
+    /// Send the result to the waiting caller or add the object to the
+    /// pool if nobody is waiting for it anymore.
+    fn finish(&mut self, result: Option<CreateResult<M>>) {
+        if let Some(tx) = self.tx.take() {
+            if let Err(result) = tx.send(result) {
+                if let Some(pool) = self.pool.upgrade() {
+                    pool.adopt(result.and_then(Result::ok), self.generation);
+                }
+            }
+        }
+    }
+}
+
+impl<M: Manager> Drop for CreateTask<M> {
+    fn drop(&mut self) {
+        // The creation panicked or the task was never run.
+        self.finish(None);
+    }
+}
+
+/// The receiving half of a spawned object creation. If it is dropped
+/// before the result has been received the created object is added to
+/// the pool.
+struct PendingCreate<'a, M: Manager> {
+    rx: Option<CreateReceiver<M>>,
+    pool: &'a PoolInner<M>,
+    generation: usize,
+}
+
+impl<M: Manager> Drop for PendingCreate<'_, M> {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            // If the result is already there the task can no longer add
+            // the object to the pool.
+            if let Ok(result) = rx.try_recv() {
+                self.pool
+                    .adopt(result.and_then(Result::ok), self.generation);
+            }
+        }
+    }
 }
 
 /// A generic object and connection pool.
@@ -201,14 +520,16 @@ struct PoolInner<M: Manager> {
 /// and uses reference counting for its internal state.
 pub struct Pool<M: Manager, W: From<Object<M>> = Object<M>> {
     inner: Arc<PoolInner<M>>,
-    _wrapper: PhantomData<W>,
+    // The pool only produces wrappers so it is `Send` and `Sync`
+    // independently of `W`.
+    _wrapper: PhantomData<fn() -> W>,
 }
 
 impl<M: Manager, W: From<Object<M>>> Clone for Pool<M, W> {
     fn clone(&self) -> Pool<M, W> {
         Pool {
             inner: self.inner.clone(),
-            _wrapper: PhantomData::default(),
+            _wrapper: PhantomData,
         }
     }
 }
@@ -225,116 +546,626 @@ impl<M: Manager, W: From<Object<M>>> Pool<M, W> {
     /// specifies the maximum numbers of objects to be created and
     /// timeouts.
     pub fn from_config(manager: M, config: PoolConfig) -> Pool<M, W> {
+        Self::from_parts(manager, config, PoolOptions::default())
+    }
+    /// Create a `PoolBuilder` for the given `manager`. Unlike
+    /// `Pool::from_config` the builder validates the configuration
+    /// before creating the pool.
+    pub fn builder(manager: M) -> PoolBuilder<M, W> {
+        PoolBuilder::new(manager)
+    }
+    fn from_parts(manager: M, config: PoolConfig, options: PoolOptions<M>) -> Pool<M, W> {
+        let autoscaler = options
+            .autoscale
+            .map(|autoscale| Autoscaler::new(autoscale, config.max_size));
+        let create_limiter = CreateLimiter::new(&config);
+        let semaphore = PrioritySemaphore::new(config.max_size, config.overflow, options.scheduler);
+        if let Some(autoscaler) = &autoscaler {
+            semaphore.set_limit(autoscaler.initial_limit());
+        }
         Pool {
             inner: Arc::new(PoolInner {
-                manager: Box::new(manager),
-                queue: std::sync::Mutex::new(Vec::with_capacity(config.max_size)),
+                manager: RwLock::new(Arc::new(manager)),
+                queue: IdleQueue::with_stashes(
+                    config.max_size + config.overflow,
+                    config.worker_stash.unwrap_or(0),
+                ),
                 size: AtomicUsize::new(0),
                 available: AtomicIsize::new(0),
-                semaphore: Semaphore::new(config.max_size),
+                semaphore,
                 config,
+                hooks: options.hooks,
+                leak_detector: options.leak_detector,
+                poison_on_panic: options.poison_on_panic,
+                poisoned: AtomicUsize::new(0),
+                generation: AtomicUsize::new(0),
+                used_weight: AtomicUsize::new(0),
+                autoscaler,
+                create_limiter,
+                returned: Notify::new(),
+                spawn_create: options.spawn_create,
+                idle_notify: options.idle_notify,
+                context: RuntimeContext::current(),
+                observers: options.observers,
             }),
-            _wrapper: PhantomData::default(),
+            _wrapper: PhantomData,
         }
     }
     /// Retrieve object from pool or wait for one to become available.
-    pub async fn get(&self) -> Result<W, PoolError<M::Error>> {
-        self.timeout_get(&self.inner.config.timeouts).await
+    #[track_caller]
+    pub fn get(&self) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        self.timeout_get_at(&self.inner.config.timeouts, Location::caller())
+    }
+    /// Retrieve object from pool or wait for one to become available.
+    /// When an object is returned to the pool it is handed to the waiting
+    /// caller with the highest priority. Callers which have been waiting
+    /// for a long time gain priority so they are not starved (see
+    /// `PoolBuilder::priority_aging`).
+    #[track_caller]
+    pub fn get_with_priority(
+        &self,
+        priority: Priority,
+    ) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        self.get_weighted_at(
+            1,
+            priority,
+            None,
+            &self.inner.config.timeouts,
+            Location::caller(),
+        )
+    }
+    /// Retrieve object on behalf of `consumer`. The number of objects a
+    /// consumer may hold or wait for at the same time is limited by the
+    /// quotas configured via `PoolBuilder::consumer_quota` and
+    /// `PoolBuilder::consumer_quota_for`. Calls exceeding the quota fail
+    /// immediately with `PoolError::QuotaExceeded`. When an object is
+    /// returned to the pool it is handed to the waiting consumer which
+    /// currently holds the fewest objects.
+    #[track_caller]
+    pub fn get_for(
+        &self,
+        consumer: &str,
+    ) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        let location = Location::caller();
+        let consumer = Some(Consumer::from(consumer));
+        async move {
+            self.get_weighted_at(
+                1,
+                Priority::Normal,
+                consumer,
+                &self.inner.config.timeouts,
+                location,
+            )
+            .await
+        }
     }
     /// Retrieve object from the pool and do not wait if there is currently
     /// no object available and the maximum pool size has been reached.
-    pub async fn try_get(&self) -> Result<W, PoolError<M::Error>> {
-        let mut timeouts = self.inner.config.timeouts.clone();
-        timeouts.wait = Some(Duration::from_secs(0));
-        self.timeout_get(&timeouts).await
+    #[track_caller]
+    pub fn try_get(&self) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        let location = Location::caller();
+        async move {
+            let mut timeouts = self.inner.config.timeouts.clone();
+            timeouts.wait = Some(Duration::from_secs(0));
+            self.timeout_get_at(&timeouts, location).await
+        }
     }
     /// Retrieve object using a different timeout config than the one
     /// configured.
-    pub async fn timeout_get(&self, timeouts: &Timeouts) -> Result<W, PoolError<M::Error>> {
-        self.inner.available.fetch_sub(1, Ordering::Relaxed);
-
-        let mut obj = Object {
+    #[track_caller]
+    pub fn timeout_get<'a>(
+        &'a self,
+        timeouts: &'a Timeouts,
+    ) -> impl Future<Output = Result<W, PoolError<M::Error>>> + 'a {
+        self.timeout_get_at(timeouts, Location::caller())
+    }
+    /// Retrieve object which consumes `weight` slots of the pool instead
+    /// of one. This makes it possible to limit the total capacity used by
+    /// objects of different sizes. A weight of zero is treated as one.
+    /// Requesting a weight exceeding `max_size` fails with
+    /// `PoolError::Timeout(TimeoutType::Wait)`.
+    #[track_caller]
+    pub fn get_weighted(
+        &self,
+        weight: usize,
+    ) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        let location = Location::caller();
+        async move {
+            let weight = match u32::try_from(weight.max(1)) {
+                Ok(w) if weight <= self.inner.config.max_size => w,
+                _ => return Err(PoolError::Timeout(TimeoutType::Wait)),
+            };
+            self.get_weighted_at(
+                weight,
+                Priority::Normal,
+                None,
+                &self.inner.config.timeouts,
+                location,
+            )
+            .await
+        }
+    }
+    /// Retrieve object from pool blocking the current thread until one
+    /// becomes available. This makes it possible to use the pool from
+    /// synchronous code without an async runtime. If the pool is
+    /// configured to use a runtime the `get` is spawned as a task so that
+    /// objects are created and recycled by the runtime. For
+    /// `Runtime::Tokio1` this is the runtime the pool was created in.
+    /// Without a runtime objects are created and recycled on the current
+    /// thread.
+    ///
+    /// This method can be called from the worker threads and the
+    /// blocking thread pool of a multi-threaded tokio runtime. It uses
+    /// `tokio::task::block_in_place` so that other tasks keep running.
+    /// Calling it from within a current-thread tokio runtime or an
+    /// async-std task fails with `PoolError::BlockingInAsyncContext` as
+    /// it would block the runtime. Only runtimes enabled via the
+    /// `rt_tokio_1` and `rt_async-std_1` features can be detected.
+    #[track_caller]
+    pub fn blocking_get(&self) -> Result<W, PoolError<M::Error>>
+    where
+        M: Send + Sync + 'static,
+        M::Type: Send + 'static,
+        M::Error: Send + 'static,
+    {
+        self.blocking_timeout_get_at(&self.inner.config.timeouts, Location::caller())
+    }
+    /// Blocking version of `Pool::timeout_get`. See `Pool::blocking_get`
+    /// for details.
+    #[track_caller]
+    pub fn blocking_timeout_get(&self, timeouts: &Timeouts) -> Result<W, PoolError<M::Error>>
+    where
+        M: Send + Sync + 'static,
+        M::Type: Send + 'static,
+        M::Error: Send + 'static,
+    {
+        self.blocking_timeout_get_at(timeouts, Location::caller())
+    }
+    fn blocking_timeout_get_at(
+        &self,
+        timeouts: &Timeouts,
+        location: &'static Location<'static>,
+    ) -> Result<W, PoolError<M::Error>>
+    where
+        M: Send + Sync + 'static,
+        M::Type: Send + 'static,
+        M::Error: Send + 'static,
+    {
+        // The object is converted to the wrapper type on the current
+        // thread so that it doesn't need to be `Send`.
+        let pool = Pool::<M> {
+            inner: self.inner.clone(),
+            _wrapper: PhantomData,
+        };
+        let timeouts = timeouts.clone();
+        let get = async move { pool.timeout_get_at(&timeouts, location).await };
+        let obj = block_in_place(|| {
+            self.inner
+                .context
+                .spawn_and_wait(&self.inner.config.runtime, get)
+        })
+        .ok_or(PoolError::BlockingInAsyncContext)?
+        .map_err(|_| PoolError::NoRuntimeSpecified)??;
+        Ok(obj.into())
+    }
+    /// Retrieve object using the given timeouts recording `location` as
+    /// the caller if leak detection is enabled.
+    async fn timeout_get_at(
+        &self,
+        timeouts: &Timeouts,
+        location: &'static Location<'static>,
+    ) -> Result<W, PoolError<M::Error>> {
+        self.get_weighted_at(1, Priority::Normal, None, timeouts, location)
+            .await
+    }
+    async fn get_weighted_at(
+        &self,
+        weight: u32,
+        priority: Priority,
+        consumer: Option<Consumer>,
+        timeouts: &Timeouts,
+        location: &'static Location<'static>,
+    ) -> Result<W, PoolError<M::Error>> {
+        let started = self.inner.observers.start();
+        let result = async {
+            self.inner.available.fetch_sub(1, Ordering::Relaxed);
+            let mut obj = self.waiting_object(weight, consumer);
+            let deadline = wait_deadline(timeouts);
+            obj.overflow = self
+                .acquire_permits(weight, priority, &obj.consumer, timeouts)
+                .await?;
+            let (manager, generation) = self.current_manager();
+            self.receive(&mut obj, &manager, generation, timeouts, deadline, location)
+                .await?;
+            Ok(obj.into())
+        }
+        .await;
+        self.inner.observe_get(&result, 1, started);
+        result
+    }
+    /// Retrieve `n` objects at once. The permits for all of them are
+    /// acquired atomically before any object is created or recycled so
+    /// that tasks needing multiple objects can't deadlock each other.
+    /// If any of the objects can't be retrieved the whole call fails.
+    /// Requesting more than `max_size` objects fails with
+    /// `PoolError::Timeout(TimeoutType::Wait)`.
+    #[track_caller]
+    pub fn get_many(
+        &self,
+        n: usize,
+    ) -> impl Future<Output = Result<Vec<W>, PoolError<M::Error>>> + '_ {
+        self.get_many_at(n, Location::caller())
+    }
+    async fn get_many_at(
+        &self,
+        n: usize,
+        location: &'static Location<'static>,
+    ) -> Result<Vec<W>, PoolError<M::Error>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let permits = match u32::try_from(n) {
+            Ok(permits) if n <= self.inner.config.max_size => permits,
+            _ => return Err(PoolError::Timeout(TimeoutType::Wait)),
+        };
+        let started = self.inner.observers.start();
+        let result = self.get_many_permits(n, permits, location).await;
+        self.inner.observe_get(&result, n, started);
+        result
+    }
+    async fn get_many_permits(
+        &self,
+        n: usize,
+        permits: u32,
+        location: &'static Location<'static>,
+    ) -> Result<Vec<W>, PoolError<M::Error>> {
+        self.inner
+            .available
+            .fetch_sub(n as isize, Ordering::Relaxed);
+        let mut objs = (0..n)
+            .map(|_| self.waiting_object(1, None))
+            .collect::<Vec<_>>();
+        let deadline = wait_deadline(&self.inner.config.timeouts);
+        let overflow = self
+            .acquire_permits(
+                permits,
+                Priority::Normal,
+                &None,
+                &self.inner.config.timeouts,
+            )
+            .await?;
+        let (manager, generation) = self.current_manager();
+        // Every object owns one of the acquired permits from now on.
+        for obj in objs.iter_mut() {
+            obj.state = ObjectState::Receiving;
+            obj.overflow = overflow;
+        }
+        for obj in objs.iter_mut() {
+            self.receive(
+                obj,
+                &manager,
+                generation,
+                &self.inner.config.timeouts,
+                deadline,
+                location,
+            )
+            .await?;
+        }
+        Ok(objs.into_iter().map(Into::into).collect())
+    }
+    /// Create an empty object which is waiting for `weight` permits.
+    fn waiting_object(&self, weight: u32, consumer: Option<Consumer>) -> Object<M> {
+        Object {
             obj: None,
             state: ObjectState::Waiting,
             pool: Arc::downgrade(&self.inner),
-        };
-
+            checkout: None,
+            poisoned: false,
+            generation: 0,
+            metrics: Metrics::new(),
+            weight,
+            consumer,
+            overflow: false,
+        }
+    }
+    /// Get the current manager and generation. This is called once the
+    /// permits have been acquired so callers which had to wait pick up a
+    /// manager set in the meantime. The same manager is then used for the
+    /// rest of the `get` call even if it is replaced again. Objects
+    /// created by it are tagged with the generation which was current
+    /// when the manager was retrieved.
+    fn current_manager(&self) -> (Arc<M>, usize) {
+        let manager = self.inner.manager.read().unwrap();
+        (
+            manager.clone(),
+            self.inner.generation.load(Ordering::Relaxed),
+        )
+    }
+    /// Acquire `n` permits of the semaphore and forget them. The caller
+    /// is responsible for adding them back. Returns true if overflow
+    /// permits were acquired.
+    async fn acquire_permits(
+        &self,
+        n: u32,
+        priority: Priority,
+        consumer: &Option<Consumer>,
+        timeouts: &Timeouts,
+    ) -> Result<bool, PoolError<M::Error>> {
         let non_blocking = match timeouts.wait {
             Some(t) => t.as_nanos() == 0,
             None => false,
         };
+        let wait = self
+            .inner
+            .autoscaler
+            .as_ref()
+            .map(|autoscaler| autoscaler.start(self.inner.semaphore.available_permits()));
+        self.inner.autoscale();
 
-        let permit = if non_blocking {
-            self.inner.semaphore.try_acquire().map_err(|e| match e {
-                TryAcquireError::Closed => PoolError::Closed,
-                TryAcquireError::NoPermits => PoolError::Timeout(TimeoutType::Wait),
-            })?
+        let result = if non_blocking {
+            self.try_acquire_permits(n, consumer)
+                .map_err(PoolError::from_acquire_error)
         } else {
             apply_timeout(
                 &self.inner.config.runtime,
                 TimeoutType::Wait,
-                self.inner.config.timeouts.wait,
-                async {
+                timeouts.wait,
+                leak::watch(
+                    self.inner.leak_detector.as_ref(),
+                    &self.inner.config.runtime,
                     self.inner
-                        .semaphore
-                        .acquire()
-                        .await
-                        .map_err(|_| PoolError::Closed)
-                },
+                        .autoscale_while(self.wait_for_permits(n, priority, consumer)),
+                ),
             )
-            .await?
+            .await
         };
 
-        permit.forget();
-
+        drop(wait);
+        self.inner.autoscale();
+        result
+    }
+    /// Acquire `n` permits without waiting. Overflow permits are used if
+    /// the pool is exhausted and `overflow_after` is not set.
+    fn try_acquire_permits(
+        &self,
+        n: u32,
+        consumer: &Option<Consumer>,
+    ) -> Result<bool, AcquireError> {
+        let semaphore = &self.inner.semaphore;
+        match semaphore.try_acquire_many(n, consumer) {
+            Err(AcquireError::NoPermits) if self.inner.overflow_after().is_none() => {
+                semaphore.try_acquire_overflow(n, consumer).map(|()| true)
+            }
+            result => result.map(|()| false),
+        }
+    }
+    /// Wait for `n` permits. Once `overflow_after` has elapsed overflow
+    /// permits are used if available. Otherwise the caller keeps its
+    /// position in the queue and continues waiting.
+    async fn wait_for_permits(
+        &self,
+        n: u32,
+        priority: Priority,
+        consumer: &Option<Consumer>,
+    ) -> Result<bool, PoolError<M::Error>> {
+        let semaphore = &self.inner.semaphore;
+        let overflow_after = match self.inner.overflow_after() {
+            _ if self.inner.config.overflow == 0 => None,
+            Some(overflow_after) => Some(overflow_after),
+            None => match self.try_acquire_permits(n, consumer) {
+                Err(AcquireError::NoPermits) => None,
+                result => return result.map_err(PoolError::from_acquire_error),
+            },
+        };
+        let mut acquire = Box::pin(semaphore.acquire_many(n, priority, consumer));
+        if let Some(overflow_after) = overflow_after {
+            match self
+                .inner
+                .config
+                .runtime
+                .timeout(overflow_after, acquire.as_mut())
+                .await
+            {
+                Ok(result) => {
+                    return result
+                        .map(|()| false)
+                        .map_err(PoolError::from_acquire_error)
+                }
+                Err(TimeoutError::NoRuntime) => return Err(PoolError::NoRuntimeSpecified),
+                Err(TimeoutError::Timeout) => {
+                    if semaphore.try_acquire_overflow(n, consumer).is_ok() {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        acquire
+            .await
+            .map(|()| false)
+            .map_err(PoolError::from_acquire_error)
+    }
+    /// Wait for a create slot of `limiter`. Returns `None` if an idle
+    /// object becomes available first.
+    async fn create_slot(
+        &self,
+        limiter: &CreateLimiter,
+    ) -> Result<Option<CreateSlot>, PoolError<M::Error>> {
+        let mut acquire = Box::pin(limiter.acquire(&self.inner.config.runtime));
+        loop {
+            let returned = self.inner.returned.notified();
+            tokio::pin!(returned);
+            returned.as_mut().enable();
+            if !self.inner.queue.is_empty() {
+                return Ok(None);
+            }
+            let slot = std::future::poll_fn(|cx| {
+                if let Poll::Ready(slot) = acquire.as_mut().poll(cx) {
+                    return Poll::Ready(Some(slot));
+                }
+                returned.as_mut().poll(cx).map(|()| None)
+            })
+            .await;
+            match slot {
+                Some(Ok(slot)) => return Ok(Some(slot)),
+                Some(Err(_)) => return Err(PoolError::NoRuntimeSpecified),
+                None => continue,
+            }
+        }
+    }
+    /// Create an object in a separate task and wait for it unless an
+    /// idle object becomes available first. In that case `None` is
+    /// returned and the created object is added to the pool later.
+    async fn race_create(
+        &self,
+        spawn: SpawnCreate<M>,
+        manager: &Arc<M>,
+        slot: Option<CreateSlot>,
+        generation: usize,
+        timeout: Option<Duration>,
+    ) -> Result<Option<M::Type>, PoolError<M::Error>> {
+        let rx = spawn(&self.inner, manager.clone(), slot, generation, timeout)
+            .ok_or(PoolError::NoRuntimeSpecified)?;
+        let mut pending = PendingCreate {
+            rx: Some(rx),
+            pool: &self.inner,
+            generation,
+        };
+        loop {
+            let returned = self.inner.returned.notified();
+            tokio::pin!(returned);
+            returned.as_mut().enable();
+            if !self.inner.queue.is_empty() {
+                return Ok(None);
+            }
+            let result = std::future::poll_fn(|cx| {
+                let rx = pending.rx.as_mut().unwrap();
+                if let Poll::Ready(result) = Pin::new(rx).poll(cx) {
+                    return Poll::Ready(Some(result));
+                }
+                returned.as_mut().poll(cx).map(|()| None)
+            })
+            .await;
+            if let Some(result) = result {
+                pending.rx = None;
+                match result {
+                    Ok(Some(result)) => return result.map(Some),
+                    _ => return Err(PoolError::CreateAborted),
+                }
+            }
+        }
+    }
+    /// Fill an object which owns a permit by recycling an idle object or
+    /// creating a new one. Waiting for a create slot is limited by the
+    /// wait timeout `deadline` of the `get` call.
+    async fn receive(
+        &self,
+        obj: &mut Object<M>,
+        manager: &Arc<M>,
+        generation: usize,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+        location: &'static Location<'static>,
+    ) -> Result<(), PoolError<M::Error>> {
         loop {
             obj.state = ObjectState::Receiving;
-            let inner_obj = {
-                let mut queue = self.inner.queue.lock().unwrap();
-                queue.pop()
+            // Overflow objects are always created so that idle objects
+            // don't get destroyed when the overflow object is returned.
+            let inner_obj = if obj.overflow {
+                None
+            } else {
+                self.inner.queue.pop()
             };
             match inner_obj {
                 Some(inner_obj) => {
                     // Recycle existing object
                     obj.state = ObjectState::Recycling;
-                    obj.obj = Some(inner_obj);
-                    match apply_timeout(
-                        &self.inner.config.runtime,
-                        TimeoutType::Recycle,
-                        self.inner.config.timeouts.recycle,
-                        self.inner.manager.recycle(&mut obj),
-                    )
-                    .await
-                    {
-                        Ok(_) => break,
-                        Err(_) => {
-                            self.inner.available.fetch_sub(1, Ordering::Relaxed);
-                            self.inner.size.fetch_sub(1, Ordering::Relaxed);
-                            continue;
-                        }
+                    obj.obj = Some(inner_obj.obj);
+                    obj.generation = inner_obj.generation;
+                    obj.metrics = inner_obj.metrics;
+                    let recycled = obj.generation == self.inner.generation.load(Ordering::Relaxed)
+                        && apply_timeout(
+                            &self.inner.config.runtime,
+                            TimeoutType::Recycle,
+                            timeouts.recycle,
+                            manager.recycle(obj),
+                        )
+                        .await
+                        .is_ok()
+                        && Hooks::<M>::run(&self.inner.hooks.post_recycle, obj).is_ok();
+                    if recycled {
+                        obj.metrics.recycle();
+                        break;
                     }
+                    self.inner.available.fetch_sub(1, Ordering::Relaxed);
+                    self.inner.size.fetch_sub(1, Ordering::Relaxed);
                 }
                 None => {
+                    // Wait for permission to create an object unless an
+                    // idle object becomes available first.
+                    let slot = match &self.inner.create_limiter {
+                        Some(limiter) => match apply_timeout(
+                            &self.inner.config.runtime,
+                            TimeoutType::Wait,
+                            deadline
+                                .map(|deadline| deadline.saturating_duration_since(Instant::now())),
+                            self.create_slot(limiter),
+                        )
+                        .await?
+                        {
+                            Some(slot) => Some(slot),
+                            None => continue,
+                        },
+                        None => None,
+                    };
                     // Create new object
                     obj.state = ObjectState::Creating;
+                    obj.generation = generation;
+                    obj.metrics = Metrics::new();
                     self.inner.available.fetch_add(1, Ordering::Relaxed);
                     self.inner.size.fetch_add(1, Ordering::Relaxed);
-                    obj.obj = Some(
-                        apply_timeout(
-                            &self.inner.config.runtime,
-                            TimeoutType::Create,
-                            self.inner.config.timeouts.create,
-                            self.inner.manager.create(),
-                        )
-                        .await?,
-                    );
+                    let created = match self.inner.spawn_create.filter(|_| !obj.overflow) {
+                        Some(spawn) => {
+                            match self
+                                .race_create(spawn, manager, slot, generation, timeouts.create)
+                                .await?
+                            {
+                                Some(created) => created,
+                                // An idle object became available first. The
+                                // object being created is added to the pool
+                                // once it is ready.
+                                None => continue,
+                            }
+                        }
+                        None => {
+                            let _slot = slot;
+                            apply_timeout(
+                                &self.inner.config.runtime,
+                                TimeoutType::Create,
+                                timeouts.create,
+                                manager.create(),
+                            )
+                            .await?
+                        }
+                    };
+                    self.inner.observers.notify(Event::Create);
+                    obj.obj = Some(created);
+                    Hooks::<M>::run(&self.inner.hooks.post_create, obj)
+                        .map_err(PoolError::PostCreateHook)?;
                     break;
                 }
             }
         }
 
         obj.state = ObjectState::Ready;
-        Ok(obj.into())
+        self.inner
+            .used_weight
+            .fetch_add(obj.weight as usize, Ordering::Relaxed);
+        obj.checkout = self
+            .inner
+            .leak_detector
+            .as_ref()
+            .map(|leak_detector| leak_detector.checkout(location));
+        Ok(())
     }
     /// Close the pool
     ///
@@ -348,20 +1179,90 @@ impl<M: Manager, W: From<Object<M>>> Pool<M, W> {
     pub fn is_closed(&self) -> bool {
         self.inner.is_closed()
     }
+    /// Invalidate all objects of the pool without closing it.
+    ///
+    /// Idle objects are dropped immediately and objects which are
+    /// currently checked out are dropped when they are returned to the
+    /// pool. Subsequent calls to `Pool::get` create fresh objects using
+    /// `Manager::create`. This is useful after a credential rotation or a
+    /// failover of the backend.
+    pub fn invalidate_all(&self) {
+        self.inner.generation.fetch_add(1, Ordering::Relaxed);
+        self.inner.clear();
+    }
+    /// Remove all idle objects for which `f` returns false. The objects
+    /// which are currently checked out are not affected. Returns the
+    /// number of removed objects.
+    pub fn retain(&self, mut f: impl FnMut(&M::Type, Metrics) -> bool) -> usize {
+        let removed = {
+            let mut queue = self.inner.queue.lock_all();
+            let removed = queue.remove_if(|obj| !f(&obj.obj, obj.metrics));
+            self.inner.size.fetch_sub(removed.len(), Ordering::Relaxed);
+            self.inner
+                .available
+                .fetch_sub(removed.len() as isize, Ordering::Relaxed);
+            removed
+        };
+        // The removed objects are dropped without holding the lock.
+        removed.len()
+    }
+    /// Get the current limit of the pool. This equals `max_size` unless
+    /// autoscaling has been enabled via `PoolBuilder::autoscale`.
+    pub fn limit(&self) -> usize {
+        self.inner.semaphore.limit()
+    }
     /// Retrieve status of the pool
     pub fn status(&self) -> Status {
         let max_size = self.inner.config.max_size;
         let size = self.inner.size.load(Ordering::Relaxed);
         let available = self.inner.available.load(Ordering::Relaxed);
+        let poisoned = self.inner.poisoned.load(Ordering::Relaxed);
+        let used_weight = self.inner.used_weight.load(Ordering::Relaxed);
+        let waiting = self.inner.semaphore.waiting();
+        let overflow = self.inner.semaphore.overflow();
         Status {
             max_size,
             size,
             available,
+            poisoned,
+            used_weight,
+            waiting,
+            overflow,
+        }
+    }
+    /// List the objects which are currently checked out starting with
+    /// the one which has been checked out the longest. The list is always
+    /// empty unless leak detection has been enabled via
+    /// `PoolBuilder::leak_detection`.
+    pub fn checked_out(&self) -> Vec<CheckedOut> {
+        match &self.inner.leak_detector {
+            Some(leak_detector) => leak_detector.checked_out(),
+            None => Vec::new(),
         }
     }
     /// Get manager of the pool
-    pub fn manager(&self) -> &M {
-        &*self.inner.manager
+    pub fn manager(&self) -> ManagerRef<'_, M> {
+        ManagerRef {
+            manager: self.inner.manager(),
+            _pool: PhantomData,
+        }
+    }
+    /// Replace the manager of the pool. Objects which are currently being
+    /// created or recycled finish using the old manager. If `invalidate`
+    /// is set all existing objects are invalidated just like when calling
+    /// `Pool::invalidate_all` so only objects created by the new manager
+    /// are handed out afterwards.
+    pub fn set_manager(&self, manager: M, invalidate: bool) {
+        {
+            let mut current = self.inner.manager.write().unwrap();
+            *current = Arc::new(manager);
+            if invalidate {
+                self.inner.generation.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if invalidate {
+            self.inner.clear();
+        }
     }
 }
 
@@ -378,19 +1279,182 @@ impl<M: Manager> PoolInner<M> {
     }
     /// Remove all objects which are currently part of the pool.
     fn clear(&self) {
-        let mut queue = self.queue.lock().unwrap();
-        self.size.fetch_sub(queue.len(), Ordering::Relaxed);
-        self.available
-            .fetch_sub(queue.len() as isize, Ordering::Relaxed);
-        queue.clear();
+        let removed = {
+            let mut queue = self.queue.lock_all();
+            let removed = queue.take_all();
+            self.size.fetch_sub(removed.len(), Ordering::Relaxed);
+            self.available
+                .fetch_sub(removed.len() as isize, Ordering::Relaxed);
+            removed
+        };
+        // The removed objects are dropped without holding the lock.
+        drop(removed);
+    }
+    /// Get the current manager
+    fn manager(&self) -> Arc<M> {
+        self.manager.read().unwrap().clone()
+    }
+    /// Unregister a checkout from the leak detector
+    fn checkin(&self, checkout: Option<u64>) {
+        if let (Some(leak_detector), Some(id)) = (&self.leak_detector, checkout) {
+            leak_detector.checkin(id);
+        }
+    }
+    /// Wake up everyone waiting for an object to be returned to the
+    /// queue.
+    fn notify_returned(&self) {
+        self.returned.notify_waiters();
+        if let Some(idle_notify) = &self.idle_notify {
+            idle_notify.notify_waiters();
+        }
+    }
+    /// Remove a single idle object from the pool and return it.
+    fn pop_idle(&self) -> Option<M::Type> {
+        let obj = self.queue.pop()?;
+        self.size.fetch_sub(1, Ordering::Relaxed);
+        self.available.fetch_sub(1, Ordering::Relaxed);
+        Some(obj.obj)
     }
     /// Returns true if the pool has been closed
     fn is_closed(&self) -> bool {
-        matches!(
-            self.semaphore.try_acquire_many(0),
-            Err(TryAcquireError::Closed)
-        )
+        self.semaphore.is_closed()
+    }
+    /// Report the result of a `get` call for `n` objects to the
+    /// observers. `started` is returned by `Observers::start`.
+    fn observe_get<T>(
+        &self,
+        result: &Result<T, PoolError<M::Error>>,
+        n: usize,
+        started: Option<Instant>,
+    ) {
+        let started = match started {
+            Some(started) => started,
+            None => return,
+        };
+        match result {
+            Ok(_) => {
+                let duration = started.elapsed();
+                for _ in 0..n {
+                    self.observers.notify(Event::Get { duration });
+                }
+            }
+            Err(PoolError::Timeout(timeout_type)) => {
+                self.observers.notify(Event::Timeout(*timeout_type))
+            }
+            Err(_) => {}
+        }
+    }
+    /// Let the autoscaler decide about the limit of the pool if
+    /// autoscaling is enabled.
+    fn autoscale(&self) {
+        if let Some(autoscaler) = &self.autoscaler {
+            if let Some(limit) = autoscaler.evaluate(self.semaphore.limit()) {
+                self.set_limit(limit);
+            }
+        }
+    }
+    /// Wait for `future` and let the autoscaler decide about the limit
+    /// of the pool periodically in the meantime. Without a runtime the
+    /// future is awaited as is.
+    async fn autoscale_while<F: Future>(&self, future: F) -> F::Output {
+        let interval = match &self.autoscaler {
+            Some(autoscaler) => autoscaler.interval(),
+            None => return future.await,
+        };
+        tokio::pin!(future);
+        loop {
+            match self.config.runtime.timeout(interval, future.as_mut()).await {
+                Ok(output) => return output,
+                Err(TimeoutError::Timeout) => self.autoscale(),
+                Err(TimeoutError::NoRuntime) => return future.await,
+            }
+        }
     }
+    /// Change the limit of the pool dropping idle objects which exceed
+    /// the new limit.
+    fn set_limit(&self, limit: usize) {
+        self.semaphore.set_limit(limit);
+        let removed = {
+            let mut queue = self.queue.lock_all();
+            let size = self.size.load(Ordering::Relaxed);
+            let surplus = size.saturating_sub(limit).min(queue.len());
+            self.size.fetch_sub(surplus, Ordering::Relaxed);
+            self.available
+                .fetch_sub(surplus as isize, Ordering::Relaxed);
+            queue.take_oldest(surplus)
+        };
+        // The removed objects are dropped without holding the lock.
+        drop(removed);
+    }
+    /// Add an object which has been created for a caller who stopped
+    /// waiting for it to the pool. The object is discarded if it is
+    /// `None`, the post create hooks fail, the pool has been invalidated
+    /// or the pool is already full.
+    fn adopt(&self, obj: Option<M::Type>, generation: usize) {
+        if obj.is_some() {
+            self.observers.notify(Event::Create);
+        }
+        let obj = obj.and_then(|mut obj| {
+            let full = self.size.load(Ordering::Relaxed)
+                > self.semaphore.limit() + self.semaphore.overflow();
+            let keep = !full
+                && generation == self.generation.load(Ordering::Relaxed)
+                && Hooks::<M>::run(&self.hooks.post_create, &mut obj).is_ok();
+            if keep {
+                Some(obj)
+            } else {
+                None
+            }
+        });
+        match obj {
+            Some(obj) => {
+                self.queue.push(ObjectInner {
+                    obj,
+                    generation,
+                    metrics: Metrics::new(),
+                });
+                self.notify_returned();
+                self.clean_up();
+            }
+            None => {
+                self.size.fetch_sub(1, Ordering::Relaxed);
+                self.available.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+    /// Give back the permits of an object
+    fn release(&self, weight: u32, consumer: &Option<Consumer>, overflow: bool) {
+        if overflow {
+            self.semaphore
+                .add_overflow_permits(weight as usize, consumer);
+        } else {
+            self.semaphore.add_permits(weight as usize, consumer);
+        }
+    }
+    /// Get the time `Pool::get` waits before using overflow permits
+    fn overflow_after(&self) -> Option<Duration> {
+        self.config.overflow_after.filter(|t| t.as_nanos() > 0)
+    }
+}
+
+impl<E> PoolError<E> {
+    fn from_acquire_error(e: AcquireError) -> Self {
+        match e {
+            AcquireError::Closed => Self::Closed,
+            AcquireError::NoPermits => Self::Timeout(TimeoutType::Wait),
+            AcquireError::QuotaExceeded => Self::QuotaExceeded,
+        }
+    }
+}
+
+/// Get the point in time at which the wait timeout of a `get` call
+/// started now expires. A wait timeout of zero only means that the call
+/// doesn't wait for permits so no deadline is returned in that case.
+fn wait_deadline(timeouts: &Timeouts) -> Option<Instant> {
+    timeouts
+        .wait
+        .filter(|t| t.as_nanos() > 0)
+        .map(|t| Instant::now() + t)
 }
 
 async fn apply_timeout<O, E>(
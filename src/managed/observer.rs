@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use super::TimeoutType;
+
+/// An event of a managed pool. Events are reported to the observers
+/// registered via `PoolBuilder::observer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// An object was handed out by a `get` call
+    Get {
+        /// The time the `get` call took including waiting for, creating
+        /// and recycling the object
+        duration: Duration,
+    },
+    /// An object was returned to the pool
+    Return,
+    /// A new object was created
+    Create,
+    /// A `get` call failed because of a timeout
+    Timeout(TimeoutType),
+}
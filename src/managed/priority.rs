@@ -0,0 +1,542 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{oneshot, Semaphore, TryAcquireError};
+
+use crate::Waiting;
+
+/// Default interval after which waiters gain one priority level
+pub(crate) const DEFAULT_AGING: Duration = Duration::from_secs(1);
+
+/// Priority of a `Pool::get_with_priority` call. When an object is
+/// returned to the pool it is handed to the waiting caller with the highest
+/// priority. `Pool::get` uses `Priority::Normal`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Background work which can wait
+    Low,
+    /// The priority used by `Pool::get`
+    #[default]
+    Normal,
+    /// Latency sensitive work
+    High,
+}
+
+/// The consumer on whose behalf permits are acquired. See
+/// `Pool::get_for`.
+pub(crate) type Consumer = Arc<str>;
+
+/// Error returned when acquiring permits
+pub(crate) enum AcquireError {
+    /// The semaphore has been closed
+    Closed,
+    /// No permits are available right now
+    NoPermits,
+    /// The consumer already holds or waits for as many permits as its
+    /// quota allows
+    QuotaExceeded,
+}
+
+/// Configuration of the order in which waiters are served and of the
+/// per-consumer quotas
+#[derive(Clone, Debug)]
+pub(crate) struct SchedulerConfig {
+    /// Interval after which waiters gain one priority level
+    pub(crate) aging: Option<Duration>,
+    /// Quota of consumers not listed in `quotas`
+    pub(crate) default_quota: Option<usize>,
+    /// Quotas of individual consumers
+    pub(crate) quotas: HashMap<String, usize>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            aging: Some(DEFAULT_AGING),
+            default_quota: None,
+            quotas: HashMap::new(),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    fn quota(&self, consumer: &str) -> Option<usize> {
+        self.quotas.get(consumer).copied().or(self.default_quota)
+    }
+}
+
+struct Waiter {
+    priority: Priority,
+    consumer: Option<Consumer>,
+    since: Instant,
+    permits: u32,
+    tx: oneshot::Sender<()>,
+}
+
+/// The permits a consumer holds and waits for
+#[derive(Default)]
+struct Usage {
+    held: usize,
+    waiting: usize,
+}
+
+#[derive(Default)]
+struct Waiters {
+    /// One queue per priority containing the waiters in the order they
+    /// started waiting
+    queues: [BTreeMap<u64, Waiter>; 3],
+    next_id: u64,
+    usage: HashMap<Consumer, Usage>,
+    /// Permits taken out of circulation to lower the limit
+    withheld: usize,
+    /// Permits which should be taken out of circulation. Permits which
+    /// are in use are withheld when they are given back.
+    target_withheld: usize,
+}
+
+impl Waiters {
+    fn len(&self) -> usize {
+        self.queues.iter().map(BTreeMap::len).sum()
+    }
+    fn usage(&mut self, consumer: &Consumer) -> &mut Usage {
+        self.usage.entry(consumer.clone()).or_default()
+    }
+    fn held(&self, consumer: &Option<Consumer>) -> usize {
+        consumer
+            .as_ref()
+            .and_then(|consumer| self.usage.get(consumer))
+            .map_or(0, |usage| usage.held)
+    }
+    /// Update the usage of `consumer` and forget it once it neither holds
+    /// nor waits for permits.
+    fn update(&mut self, consumer: &Option<Consumer>, f: impl FnOnce(&mut Usage)) {
+        if let Some(consumer) = consumer {
+            let usage = self.usage(consumer);
+            f(usage);
+            if usage.held == 0 && usage.waiting == 0 {
+                self.usage.remove(consumer);
+            }
+        }
+    }
+}
+
+/// A semaphore which hands out permits to the waiter with the highest
+/// priority rather than in FIFO order. Waiters gain one priority level
+/// every `aging` interval so low priority waiters are not starved. Among
+/// waiters of the same priority the consumer holding the fewest permits
+/// is served first.
+///
+/// As long as nobody waits and no permits are withheld, permits which are
+/// not tied to a consumer are acquired and given back without locking
+/// `waiters`.
+pub(crate) struct PrioritySemaphore {
+    semaphore: Semaphore,
+    permits: usize,
+    /// Permits for objects exceeding `max_size`. Nobody waits for these.
+    overflow: Semaphore,
+    max_overflow: usize,
+    waiters: Mutex<Waiters>,
+    /// The number of waiters. Mirrors `waiters` for the lock free path.
+    queued: AtomicUsize,
+    /// Set while permits which are given back are withheld to lower the
+    /// limit. Mirrors `waiters` for the lock free path.
+    withholding: AtomicBool,
+    config: SchedulerConfig,
+}
+
+impl PrioritySemaphore {
+    pub(crate) fn new(permits: usize, overflow: usize, mut config: SchedulerConfig) -> Self {
+        config.aging = config.aging.filter(|aging| aging.as_nanos() > 0);
+        Self {
+            semaphore: Semaphore::new(permits),
+            permits,
+            overflow: Semaphore::new(overflow),
+            max_overflow: overflow,
+            waiters: Mutex::new(Waiters::default()),
+            queued: AtomicUsize::new(0),
+            withholding: AtomicBool::new(false),
+            config,
+        }
+    }
+    /// Acquire `n` permits without waiting. This fails if other callers
+    /// are already waiting for permits. The permits must be given back
+    /// using `add_permits`.
+    pub(crate) fn try_acquire_many(
+        &self,
+        n: u32,
+        consumer: &Option<Consumer>,
+    ) -> Result<(), AcquireError> {
+        if consumer.is_none() && self.queued.load(Ordering::SeqCst) == 0 {
+            return self.try_acquire_uncontended(n);
+        }
+        let mut waiters = self.waiters.lock().unwrap();
+        self.check_quota(&mut waiters, n, consumer)?;
+        if waiters.len() > 0 {
+            return Err(AcquireError::NoPermits);
+        }
+        self.try_acquire_unqueued(&mut waiters, n, consumer)
+    }
+    /// Wait for `n` permits. The permits must be given back using
+    /// `add_permits`.
+    pub(crate) async fn acquire_many(
+        &self,
+        n: u32,
+        priority: Priority,
+        consumer: &Option<Consumer>,
+    ) -> Result<(), AcquireError> {
+        if consumer.is_none() && self.queued.load(Ordering::SeqCst) == 0 {
+            match self.try_acquire_uncontended(n) {
+                Err(AcquireError::NoPermits) => {}
+                result => return result,
+            }
+        }
+        let (id, rx) = {
+            let mut waiters = self.waiters.lock().unwrap();
+            self.check_quota(&mut waiters, n, consumer)?;
+            if waiters.len() == 0 {
+                match self.try_acquire_unqueued(&mut waiters, n, consumer) {
+                    Err(AcquireError::NoPermits) => {}
+                    result => return result,
+                }
+            }
+            let (tx, rx) = oneshot::channel();
+            let id = waiters.next_id;
+            waiters.next_id += 1;
+            waiters.queues[priority as usize].insert(
+                id,
+                Waiter {
+                    priority,
+                    consumer: consumer.clone(),
+                    since: Instant::now(),
+                    permits: n,
+                    tx,
+                },
+            );
+            waiters.update(consumer, |usage| usage.waiting += n as usize);
+            // Permits given back without locking after this point are
+            // handed out by the releasing caller.
+            self.publish(&waiters);
+            fence(Ordering::SeqCst);
+            // The new waiter might be served before the ones which are
+            // already waiting.
+            self.dispatch(&mut waiters);
+            (id, rx)
+        };
+        let mut wait = Wait {
+            semaphore: self,
+            id,
+            priority,
+            permits: n,
+            consumer: consumer.clone(),
+            rx,
+            done: false,
+        };
+        let result = (&mut wait.rx).await;
+        wait.done = true;
+        result.map_err(|_| AcquireError::Closed)
+    }
+    /// Give back permits acquired on behalf of `consumer` and hand them
+    /// to the waiters.
+    pub(crate) fn add_permits(&self, n: usize, consumer: &Option<Consumer>) {
+        if consumer.is_none() && self.is_uncontended() {
+            self.semaphore.add_permits(n);
+            // Somebody might have started waiting or lowered the limit
+            // before the permits were added. In that case they are
+            // handed out or withheld like below.
+            fence(Ordering::SeqCst);
+            if self.is_uncontended() {
+                return;
+            }
+            let mut waiters = self.waiters.lock().unwrap();
+            self.withhold_available(&mut waiters);
+            self.dispatch(&mut waiters);
+            return;
+        }
+        let mut waiters = self.waiters.lock().unwrap();
+        waiters.update(consumer, |usage| usage.held -= n);
+        self.release(&mut waiters, n);
+        self.dispatch(&mut waiters);
+    }
+    /// Get the number of permits currently handed out at most
+    pub(crate) fn limit(&self) -> usize {
+        self.permits - self.waiters.lock().unwrap().target_withheld
+    }
+    /// Change the number of permits handed out at most. When lowering
+    /// the limit permits which are in use are withheld once they are
+    /// given back.
+    pub(crate) fn set_limit(&self, limit: usize) {
+        let mut waiters = self.waiters.lock().unwrap();
+        waiters.target_withheld = self.permits - limit.min(self.permits);
+        if waiters.withheld > waiters.target_withheld {
+            let n = waiters.withheld - waiters.target_withheld;
+            waiters.withheld -= n;
+            self.semaphore.add_permits(n);
+            self.dispatch(&mut waiters);
+        } else {
+            // Permits given back without locking after this point are
+            // withheld by the releasing caller.
+            self.publish(&waiters);
+            fence(Ordering::SeqCst);
+            self.withhold_available(&mut waiters);
+            self.publish(&waiters);
+        }
+    }
+    /// Get the number of permits which are neither handed out nor
+    /// withheld
+    pub(crate) fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+    /// Acquire `n` overflow permits without waiting. The quota of
+    /// `consumer` must have been checked already by `try_acquire_many` or
+    /// `acquire_many`. The permits must be given back using
+    /// `add_overflow_permits`.
+    pub(crate) fn try_acquire_overflow(
+        &self,
+        n: u32,
+        consumer: &Option<Consumer>,
+    ) -> Result<(), AcquireError> {
+        // The usage of consumers is only tracked while holding the lock.
+        let waiters = consumer.as_ref().map(|_| self.waiters.lock().unwrap());
+        if self.is_closed() {
+            return Err(AcquireError::Closed);
+        }
+        self.overflow
+            .try_acquire_many(n)
+            .map_err(|_| AcquireError::NoPermits)?
+            .forget();
+        if let Some(mut waiters) = waiters {
+            waiters.update(consumer, |usage| usage.held += n as usize);
+        }
+        Ok(())
+    }
+    /// Give back overflow permits acquired on behalf of `consumer`
+    pub(crate) fn add_overflow_permits(&self, n: usize, consumer: &Option<Consumer>) {
+        if consumer.is_some() {
+            let mut waiters = self.waiters.lock().unwrap();
+            waiters.update(consumer, |usage| usage.held -= n);
+        }
+        self.overflow.add_permits(n);
+    }
+    /// Get the number of overflow permits in use
+    pub(crate) fn overflow(&self) -> usize {
+        self.max_overflow - self.overflow.available_permits()
+    }
+    /// Close the semaphore. All current and future waiters fail.
+    pub(crate) fn close(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        self.semaphore.close();
+        for queue in std::mem::take(&mut waiters.queues) {
+            for waiter in queue.into_values() {
+                waiters.update(&waiter.consumer, |usage| {
+                    usage.waiting -= waiter.permits as usize
+                });
+            }
+        }
+        self.publish(&waiters);
+    }
+    pub(crate) fn is_closed(&self) -> bool {
+        matches!(
+            self.semaphore.try_acquire_many(0),
+            Err(TryAcquireError::Closed)
+        )
+    }
+    /// Get the number of waiters per priority
+    pub(crate) fn waiting(&self) -> Waiting {
+        let waiters = self.waiters.lock().unwrap();
+        Waiting {
+            low: waiters.queues[Priority::Low as usize].len(),
+            normal: waiters.queues[Priority::Normal as usize].len(),
+            high: waiters.queues[Priority::High as usize].len(),
+        }
+    }
+    /// Fail if acquiring `n` more permits would exceed the quota of
+    /// `consumer`. The quota is checked before waiting so that a consumer
+    /// can't queue up more requests than it is allowed to hold.
+    fn check_quota(
+        &self,
+        waiters: &mut Waiters,
+        n: u32,
+        consumer: &Option<Consumer>,
+    ) -> Result<(), AcquireError> {
+        if self.is_closed() {
+            return Err(AcquireError::Closed);
+        }
+        let consumer = match consumer {
+            Some(consumer) => consumer,
+            None => return Ok(()),
+        };
+        if let Some(quota) = self.config.quota(consumer) {
+            let usage = waiters.usage.get(consumer);
+            let used = usage.map_or(0, |usage| usage.held + usage.waiting);
+            if used + n as usize > quota {
+                return Err(AcquireError::QuotaExceeded);
+            }
+        }
+        Ok(())
+    }
+    /// Acquire `n` permits without locking `waiters`. This must only be
+    /// used for permits which are not tied to a consumer.
+    fn try_acquire_uncontended(&self, n: u32) -> Result<(), AcquireError> {
+        match self.semaphore.try_acquire_many(n) {
+            Ok(permit) => {
+                permit.forget();
+                Ok(())
+            }
+            Err(TryAcquireError::Closed) => Err(AcquireError::Closed),
+            Err(TryAcquireError::NoPermits) => Err(AcquireError::NoPermits),
+        }
+    }
+    fn try_acquire_unqueued(
+        &self,
+        waiters: &mut Waiters,
+        n: u32,
+        consumer: &Option<Consumer>,
+    ) -> Result<(), AcquireError> {
+        match self.semaphore.try_acquire_many(n) {
+            Ok(permit) => {
+                permit.forget();
+                waiters.update(consumer, |usage| usage.held += n as usize);
+                Ok(())
+            }
+            Err(TryAcquireError::Closed) => Err(AcquireError::Closed),
+            Err(TryAcquireError::NoPermits) => Err(AcquireError::NoPermits),
+        }
+    }
+    /// Give back permits withholding them if the limit has been lowered
+    fn release(&self, waiters: &mut Waiters, n: usize) {
+        let withhold = n.min(waiters.target_withheld - waiters.withheld);
+        waiters.withheld += withhold;
+        self.semaphore.add_permits(n - withhold);
+    }
+    /// Withhold available permits until the lowered limit is reached
+    fn withhold_available(&self, waiters: &mut Waiters) {
+        let n =
+            (waiters.target_withheld - waiters.withheld).min(self.semaphore.available_permits());
+        if let Ok(n) = u32::try_from(n) {
+            if let Ok(permit) = self.semaphore.try_acquire_many(n) {
+                permit.forget();
+                waiters.withheld += n as usize;
+            }
+        }
+    }
+    /// Check if permits can be given back without locking `waiters`
+    fn is_uncontended(&self) -> bool {
+        self.queued.load(Ordering::SeqCst) == 0 && !self.withholding.load(Ordering::SeqCst)
+    }
+    /// Mirror the state of `waiters` used by the lock free path
+    fn publish(&self, waiters: &Waiters) {
+        self.queued.store(waiters.len(), Ordering::SeqCst);
+        self.withholding
+            .store(waiters.withheld < waiters.target_withheld, Ordering::SeqCst);
+    }
+    /// Hand out the available permits to the waiters with the highest
+    /// priority. A waiter which needs more permits than available blocks
+    /// all waiters with a lower priority.
+    fn dispatch(&self, waiters: &mut Waiters) {
+        let now = Instant::now();
+        while let Some((priority, id)) = self.next_waiter(waiters, now) {
+            let permits = waiters.queues[priority][&id].permits;
+            match self.semaphore.try_acquire_many(permits) {
+                Ok(permit) => permit.forget(),
+                Err(_) => break,
+            }
+            let waiter = waiters.queues[priority].remove(&id).unwrap();
+            let permits = waiter.permits as usize;
+            if waiter.tx.send(()).is_ok() {
+                waiters.update(&waiter.consumer, |usage| {
+                    usage.waiting -= permits;
+                    usage.held += permits;
+                });
+            } else {
+                waiters.update(&waiter.consumer, |usage| usage.waiting -= permits);
+                self.release(waiters, permits);
+            }
+        }
+        self.publish(waiters);
+    }
+    /// Find the waiter which is served next and return its priority and
+    /// id. The oldest waiter of every queue has the highest effective
+    /// priority within that queue, so only the oldest waiters need to be
+    /// compared unless consumers are tracked. In that case the waiters
+    /// sharing the effective priority of the oldest one are compared by
+    /// the number of permits held by their consumers.
+    fn next_waiter(&self, waiters: &Waiters, now: Instant) -> Option<(usize, u64)> {
+        let key = |id: u64, waiter: &Waiter| {
+            (
+                self.effective_priority(waiter, now),
+                Reverse(waiters.held(&waiter.consumer)),
+                Reverse(id),
+            )
+        };
+        waiters
+            .queues
+            .iter()
+            .enumerate()
+            .filter_map(|(priority, queue)| {
+                let (&id, oldest) = queue.iter().next()?;
+                let best = if waiters.usage.is_empty() {
+                    key(id, oldest)
+                } else {
+                    let effective = self.effective_priority(oldest, now);
+                    queue
+                        .iter()
+                        .take_while(|(_, waiter)| self.effective_priority(waiter, now) == effective)
+                        .map(|(&id, waiter)| key(id, waiter))
+                        .max()?
+                };
+                Some((best, priority))
+            })
+            .max()
+            .map(|((_, _, Reverse(id)), priority)| (priority, id))
+    }
+    fn effective_priority(&self, waiter: &Waiter, now: Instant) -> u128 {
+        let aged = match self.config.aging {
+            Some(aging) => now.duration_since(waiter.since).as_nanos() / aging.as_nanos(),
+            None => 0,
+        };
+        waiter.priority as u128 + aged
+    }
+}
+
+/// Removes a waiter whose future is dropped before it received its
+/// permits. Permits which were handed to it in the meantime are given
+/// back.
+struct Wait<'a> {
+    semaphore: &'a PrioritySemaphore,
+    id: u64,
+    priority: Priority,
+    permits: u32,
+    consumer: Option<Consumer>,
+    rx: oneshot::Receiver<()>,
+    done: bool,
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut waiters = self.semaphore.waiters.lock().unwrap();
+        match waiters.queues[self.priority as usize].remove(&self.id) {
+            Some(_) => {
+                let permits = self.permits as usize;
+                waiters.update(&self.consumer, |usage| usage.waiting -= permits);
+                // Waiters with a lower priority might be able to proceed
+                // now.
+                self.semaphore.dispatch(&mut waiters);
+            }
+            None => {
+                if self.rx.try_recv().is_ok() {
+                    let permits = self.permits as usize;
+                    waiters.update(&self.consumer, |usage| usage.held -= permits);
+                    self.semaphore.release(&mut waiters, permits);
+                    self.semaphore.dispatch(&mut waiters);
+                }
+            }
+        }
+    }
+}
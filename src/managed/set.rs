@@ -0,0 +1,238 @@
+use std::future::Future;
+use std::panic::Location;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{Manager, Object, Pool, PoolError, Status, TimeoutType};
+
+/// Strategy used by a `PoolSet` to select a pool for each `get`
+#[derive(Clone, Debug)]
+pub enum Strategy {
+    /// Use the pools one after another
+    RoundRobin,
+    /// Use the pool with the lowest share of objects in use relative to
+    /// its current limit. Waiting futures count as objects in use.
+    LeastInUse,
+    /// Use the pools in a round robin fashion but pick every pool as
+    /// often as its weight says. The weights are given in the order of
+    /// the pools. Pools without a weight get a weight of `1`.
+    Weighted(Vec<usize>),
+}
+
+/// Pool set configuration
+#[derive(Clone, Debug)]
+pub struct PoolSetConfig {
+    /// Strategy used to select a pool
+    pub strategy: Strategy,
+    /// Number of consecutive failures to create an object after which a
+    /// pool is skipped
+    pub max_failures: usize,
+    /// Duration for which a failing pool is skipped before it is tried
+    /// again
+    pub failure_backoff: Duration,
+}
+
+impl PoolSetConfig {
+    /// Create pool set config using the given strategy. Pools are
+    /// skipped for 5 seconds after 3 consecutive failures.
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            max_failures: 3,
+            failure_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+struct Member<M: Manager, W: From<Object<M>>> {
+    pool: Pool<M, W>,
+    weight: usize,
+    failures: AtomicUsize,
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl<M: Manager, W: From<Object<M>>> Member<M, W> {
+    fn is_failing(&self, config: &PoolSetConfig) -> bool {
+        self.failures.load(Ordering::Relaxed) >= config.max_failures
+            && matches!(
+                *self.failed_at.lock().unwrap(),
+                Some(t) if t.elapsed() < config.failure_backoff
+            )
+    }
+    /// Get the number of objects in use including waiting futures and
+    /// the current limit of the pool
+    fn load(&self) -> (isize, isize) {
+        let status = self.pool.status();
+        let in_use = status.size as isize - status.available;
+        (in_use, self.pool.limit().max(1) as isize)
+    }
+}
+
+struct PoolSetInner<M: Manager, W: From<Object<M>>> {
+    members: Vec<Member<M, W>>,
+    config: PoolSetConfig,
+    next: AtomicUsize,
+}
+
+/// A set of pools which spreads `get` calls across all of its pools
+/// using a configurable `Strategy`. Pools whose objects can't be created
+/// are skipped for a while.
+///
+/// This struct can be cloned and transferred across thread boundaries
+/// and uses reference counting for its internal state.
+pub struct PoolSet<M: Manager, W: From<Object<M>> = Object<M>> {
+    inner: Arc<PoolSetInner<M, W>>,
+}
+
+impl<M: Manager, W: From<Object<M>>> Clone for PoolSet<M, W> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<M: Manager, W: From<Object<M>>> PoolSet<M, W> {
+    /// Create new pool set from the given `pools` using `strategy`.
+    pub fn new<I>(pools: I, strategy: Strategy) -> Self
+    where
+        I: IntoIterator<Item = Pool<M, W>>,
+    {
+        Self::from_config(pools, PoolSetConfig::new(strategy))
+    }
+    /// Create new pool set from the given `pools` and `config`.
+    pub fn from_config<I>(pools: I, config: PoolSetConfig) -> Self
+    where
+        I: IntoIterator<Item = Pool<M, W>>,
+    {
+        let weights = match &config.strategy {
+            Strategy::Weighted(weights) => weights.clone(),
+            _ => Vec::new(),
+        };
+        let members = pools
+            .into_iter()
+            .enumerate()
+            .map(|(i, pool)| Member {
+                pool,
+                weight: weights.get(i).copied().unwrap_or(1),
+                failures: AtomicUsize::new(0),
+                failed_at: Mutex::new(None),
+            })
+            .collect();
+        Self {
+            inner: Arc::new(PoolSetInner {
+                members,
+                config,
+                next: AtomicUsize::new(0),
+            }),
+        }
+    }
+    /// Retrieve object from one of the pools. If a pool fails to create
+    /// an object the remaining pools are tried before giving up.
+    #[track_caller]
+    pub fn get(&self) -> impl Future<Output = Result<W, PoolError<M::Error>>> + '_ {
+        self.get_at(Location::caller())
+    }
+    async fn get_at(&self, location: &'static Location<'static>) -> Result<W, PoolError<M::Error>> {
+        let mut candidates = self.candidates();
+        // There are no candidates only if the set has no pools.
+        let mut result = Err(PoolError::NoPools);
+        while !candidates.is_empty() {
+            let index = candidates.remove(self.select(&candidates));
+            let member = &self.inner.members[index];
+            let pool = &member.pool;
+            result = pool
+                .timeout_get_at(&pool.inner.config.timeouts, location)
+                .await;
+            match &result {
+                Ok(_) => {
+                    member.failures.store(0, Ordering::Relaxed);
+                    break;
+                }
+                Err(PoolError::Backend(_))
+                | Err(PoolError::PostCreateHook(_))
+                | Err(PoolError::Timeout(TimeoutType::Create)) => {
+                    member.failures.fetch_add(1, Ordering::Relaxed);
+                    *member.failed_at.lock().unwrap() = Some(Instant::now());
+                }
+                Err(_) => break,
+            }
+        }
+        result
+    }
+    /// Get the indices of all pools which are not failing. If all pools
+    /// are failing all of them are returned.
+    fn candidates(&self) -> Vec<usize> {
+        let members = &self.inner.members;
+        let candidates = (0..members.len())
+            .filter(|&i| !members[i].is_failing(&self.inner.config))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            (0..members.len()).collect()
+        } else {
+            candidates
+        }
+    }
+    /// Select one of the candidates returning its position.
+    fn select(&self, candidates: &[usize]) -> usize {
+        let members = &self.inner.members;
+        let next = self.inner.next.fetch_add(1, Ordering::Relaxed);
+        match self.inner.config.strategy {
+            Strategy::RoundRobin => next % candidates.len(),
+            Strategy::LeastInUse => {
+                // The status changes concurrently so every pool is
+                // looked at only once.
+                let loads = candidates
+                    .iter()
+                    .map(|&i| members[i].load())
+                    .collect::<Vec<_>>();
+                (0..loads.len())
+                    .min_by(|&a, &b| {
+                        // Compare `in_use / limit` without dividing
+                        let (a_in_use, a_limit) = loads[a];
+                        let (b_in_use, b_limit) = loads[b];
+                        (a_in_use * b_limit).cmp(&(b_in_use * a_limit))
+                    })
+                    .unwrap()
+            }
+            Strategy::Weighted(_) => {
+                let total: usize = candidates.iter().map(|&i| members[i].weight).sum();
+                if total == 0 {
+                    return next % candidates.len();
+                }
+                let mut n = next % total;
+                candidates
+                    .iter()
+                    .position(|&i| {
+                        let weight = members[i].weight;
+                        if n < weight {
+                            true
+                        } else {
+                            n -= weight;
+                            false
+                        }
+                    })
+                    .unwrap()
+            }
+        }
+    }
+    /// Retrieve the pools of this set
+    pub fn pools(&self) -> impl Iterator<Item = &Pool<M, W>> {
+        self.inner.members.iter().map(|member| &member.pool)
+    }
+    /// Close all pools of this set
+    pub fn close(&self) {
+        for member in &self.inner.members {
+            member.pool.close();
+        }
+    }
+    /// Retrieve aggregated status of all pools
+    pub fn status(&self) -> Status {
+        self.inner
+            .members
+            .iter()
+            .map(|member| member.pool.status())
+            .sum()
+    }
+}
@@ -0,0 +1,409 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::Notify;
+
+use super::{apply_timeout, Manager, PoolError, Status, TimeoutType, Timeouts, Waiting};
+use crate::Runtime;
+
+/// Shared pool configuration
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize))]
+pub struct SharedPoolConfig {
+    /// Maximum number of objects
+    pub max_size: usize,
+    /// Maximum number of handles to the same object which may exist at
+    /// the same time. Values below one are treated as one.
+    pub max_borrowers: usize,
+    /// Timeouts
+    #[cfg_attr(feature = "config", serde(default))]
+    pub timeouts: Timeouts,
+    /// Runtime
+    #[cfg_attr(feature = "config", serde(skip))]
+    pub runtime: Runtime,
+}
+
+impl SharedPoolConfig {
+    /// Create shared pool config without any timeouts
+    pub fn new(max_size: usize, max_borrowers: usize) -> Self {
+        Self {
+            max_size,
+            max_borrowers,
+            timeouts: Timeouts::default(),
+            runtime: Runtime::default(),
+        }
+    }
+}
+
+struct Entry<T> {
+    obj: Arc<T>,
+    /// Number of `SharedObject` handles of this object
+    borrowers: usize,
+}
+
+struct State<T> {
+    entries: Vec<Entry<T>>,
+    /// Number of objects which are being created or recycled
+    pending: usize,
+    /// Number of `get` calls waiting for a borrower slot
+    waiting: usize,
+}
+
+/// The next step of a `get` call decided while holding the lock
+enum Reservation<T> {
+    /// Borrow an object which is already in use
+    Borrow(Arc<T>),
+    /// Recycle an idle object before borrowing it
+    Recycle(Arc<T>),
+    /// Create a new object
+    Create,
+}
+
+struct SharedPoolInner<M: Manager> {
+    manager: M,
+    config: SharedPoolConfig,
+    state: Mutex<State<M::Type>>,
+    /// Notified whenever a borrower slot becomes available
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+/// A handle to an object of a `SharedPool`. Other handles to the same
+/// object might exist at the same time, so the object can only be
+/// accessed via a shared reference. The borrower slot is given back
+/// when the handle is dropped.
+pub struct SharedObject<M: Manager> {
+    obj: Option<Arc<M::Type>>,
+    pool: Weak<SharedPoolInner<M>>,
+}
+
+impl<M: Manager> Deref for SharedObject<M> {
+    type Target = M::Type;
+    fn deref(&self) -> &M::Type {
+        self.obj.as_ref().unwrap()
+    }
+}
+
+impl<M: Manager> AsRef<M::Type> for SharedObject<M> {
+    fn as_ref(&self) -> &M::Type {
+        self
+    }
+}
+
+impl<M: Manager> Drop for SharedObject<M> {
+    fn drop(&mut self) {
+        let obj = match self.obj.take() {
+            Some(obj) => obj,
+            None => return,
+        };
+        let ptr = Arc::as_ptr(&obj);
+        // The reference is dropped first so that the object can be
+        // recycled as soon as it has no borrowers anymore.
+        drop(obj);
+        if let Some(pool) = self.pool.upgrade() {
+            let removed = {
+                let mut state = pool.state.lock().unwrap();
+                let index = state
+                    .entries
+                    .iter()
+                    .position(|entry| Arc::as_ptr(&entry.obj) == ptr)
+                    .unwrap();
+                state.entries[index].borrowers -= 1;
+                // Idle objects of a closed pool are removed.
+                if state.entries[index].borrowers == 0 && pool.is_closed() {
+                    Some(state.entries.swap_remove(index))
+                } else {
+                    None
+                }
+            };
+            // The removed object is dropped without holding the lock.
+            drop(removed);
+            pool.notify.notify_waiters();
+        }
+    }
+}
+
+/// Keeps track of an object which is being created or recycled.
+struct Pending<'a, M: Manager> {
+    pool: &'a SharedPoolInner<M>,
+    done: bool,
+}
+
+impl<M: Manager> Pending<'_, M> {
+    /// Add the object to the pool and borrow it
+    fn insert(mut self, obj: Arc<M::Type>) -> Result<Arc<M::Type>, PoolError<M::Error>> {
+        self.done = true;
+        {
+            let mut state = self.pool.state.lock().unwrap();
+            state.pending -= 1;
+            if self.pool.is_closed() {
+                drop(state);
+                self.pool.notify.notify_waiters();
+                return Err(PoolError::Closed);
+            }
+            state.entries.push(Entry {
+                obj: obj.clone(),
+                borrowers: 1,
+            });
+        }
+        // The new object might accept further borrowers.
+        self.pool.notify.notify_waiters();
+        Ok(obj)
+    }
+}
+
+impl<M: Manager> Drop for Pending<'_, M> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.pool.state.lock().unwrap().pending -= 1;
+            self.pool.notify.notify_waiters();
+        }
+    }
+}
+
+/// Decrements the number of waiting `get` calls when dropped
+struct WaitGuard<'a, M: Manager> {
+    pool: &'a SharedPoolInner<M>,
+}
+
+impl<M: Manager> Drop for WaitGuard<'_, M> {
+    fn drop(&mut self) {
+        self.pool.state.lock().unwrap().waiting -= 1;
+    }
+}
+
+impl<M: Manager> SharedPoolInner<M> {
+    fn max_borrowers(&self) -> usize {
+        self.config.max_borrowers.max(1)
+    }
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+    /// Decide how to get a borrower slot. Objects which are already in
+    /// use are preferred, starting with the one with the fewest
+    /// borrowers. New objects are only created if all existing ones are
+    /// saturated. Returns `None` if the caller has to wait.
+    fn try_reserve(
+        &self,
+        state: &mut State<M::Type>,
+    ) -> Result<Option<Reservation<M::Type>>, PoolError<M::Error>> {
+        if self.is_closed() {
+            return Err(PoolError::Closed);
+        }
+        let max_borrowers = self.max_borrowers();
+        if let Some(entry) = state
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.borrowers > 0 && entry.borrowers < max_borrowers)
+            .min_by_key(|entry| entry.borrowers)
+        {
+            entry.borrowers += 1;
+            return Ok(Some(Reservation::Borrow(entry.obj.clone())));
+        }
+        if let Some(index) = state.entries.iter().position(|entry| entry.borrowers == 0) {
+            let entry = state.entries.swap_remove(index);
+            state.pending += 1;
+            return Ok(Some(Reservation::Recycle(entry.obj)));
+        }
+        if state.entries.len() + state.pending < self.config.max_size {
+            state.pending += 1;
+            return Ok(Some(Reservation::Create));
+        }
+        Ok(None)
+    }
+    /// Wait until a borrower slot can be reserved. If `wait` is false
+    /// `PoolError::Timeout` is returned instead of waiting.
+    async fn reserve(&self, wait: bool) -> Result<Reservation<M::Type>, PoolError<M::Error>> {
+        let mut guard = None;
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(reservation) = self.try_reserve(&mut state)? {
+                    return Ok(reservation);
+                }
+                if !wait {
+                    return Err(PoolError::Timeout(TimeoutType::Wait));
+                }
+                if guard.is_none() {
+                    state.waiting += 1;
+                    guard = Some(WaitGuard { pool: self });
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A pool for objects which can be used by multiple callers at the same
+/// time, e.g. multiplexed connections. Every object is handed out to up
+/// to `max_borrowers` callers at once and new objects are only created
+/// when all existing ones are saturated.
+///
+/// Objects are recycled using `Manager::recycle` before they are handed
+/// out again after all of their handles have been dropped.
+///
+/// This struct can be cloned and transferred across thread boundaries
+/// and uses reference counting for its internal state.
+pub struct SharedPool<M: Manager> {
+    inner: Arc<SharedPoolInner<M>>,
+}
+
+impl<M: Manager> Clone for SharedPool<M> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<M: Manager> SharedPool<M> {
+    /// Create new shared pool with a given `manager`, `max_size` and
+    /// `max_borrowers` per object.
+    pub fn new(manager: M, max_size: usize, max_borrowers: usize) -> Self {
+        Self::from_config(manager, SharedPoolConfig::new(max_size, max_borrowers))
+    }
+    /// Create new shared pool with a given `manager` and `config`.
+    pub fn from_config(manager: M, config: SharedPoolConfig) -> Self {
+        Self {
+            inner: Arc::new(SharedPoolInner {
+                manager,
+                state: Mutex::new(State {
+                    entries: Vec::with_capacity(config.max_size),
+                    pending: 0,
+                    waiting: 0,
+                }),
+                config,
+                notify: Notify::new(),
+                closed: AtomicBool::new(false),
+            }),
+        }
+    }
+    /// Retrieve a handle to an object or wait for a borrower slot to
+    /// become available.
+    pub async fn get(&self) -> Result<SharedObject<M>, PoolError<M::Error>> {
+        self.timeout_get(&self.inner.config.timeouts).await
+    }
+    /// Retrieve a handle to an object and do not wait if all objects are
+    /// saturated and the maximum pool size has been reached.
+    pub async fn try_get(&self) -> Result<SharedObject<M>, PoolError<M::Error>> {
+        let mut timeouts = self.inner.config.timeouts.clone();
+        timeouts.wait = Some(std::time::Duration::from_secs(0));
+        self.timeout_get(&timeouts).await
+    }
+    /// Retrieve a handle to an object using a different timeout config
+    /// than the one configured.
+    pub async fn timeout_get(
+        &self,
+        timeouts: &Timeouts,
+    ) -> Result<SharedObject<M>, PoolError<M::Error>> {
+        let inner = self.inner.as_ref();
+        let runtime = &inner.config.runtime;
+        let wait = !matches!(timeouts.wait, Some(t) if t.as_nanos() == 0);
+        loop {
+            let reservation = apply_timeout(
+                runtime,
+                TimeoutType::Wait,
+                timeouts.wait.filter(|t| t.as_nanos() > 0),
+                inner.reserve(wait),
+            )
+            .await?;
+            let obj = match reservation {
+                Reservation::Borrow(obj) => obj,
+                Reservation::Recycle(mut obj) => {
+                    let pending = Pending {
+                        pool: inner,
+                        done: false,
+                    };
+                    // Idle objects have no handles.
+                    let recycled = apply_timeout(
+                        runtime,
+                        TimeoutType::Recycle,
+                        timeouts.recycle,
+                        inner.manager.recycle(Arc::get_mut(&mut obj).unwrap()),
+                    )
+                    .await;
+                    if recycled.is_err() {
+                        // The object is dropped and a new one is created.
+                        continue;
+                    }
+                    pending.insert(obj)?
+                }
+                Reservation::Create => {
+                    let pending = Pending {
+                        pool: inner,
+                        done: false,
+                    };
+                    let obj = apply_timeout(
+                        runtime,
+                        TimeoutType::Create,
+                        timeouts.create,
+                        inner.manager.create(),
+                    )
+                    .await?;
+                    pending.insert(Arc::new(obj))?
+                }
+            };
+            return Ok(SharedObject {
+                obj: Some(obj),
+                pool: Arc::downgrade(&self.inner),
+            });
+        }
+    }
+    /// Close the pool
+    ///
+    /// All current and future tasks waiting for objects return
+    /// `Err(PoolError::Closed)` immediately. Idle objects are dropped
+    /// right away and the others once their last handle is dropped.
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::Relaxed);
+        let removed = {
+            let mut state = self.inner.state.lock().unwrap();
+            let (removed, retained) = state
+                .entries
+                .drain(..)
+                .partition(|entry| entry.borrowers == 0);
+            state.entries = retained;
+            removed
+        };
+        // The removed objects are dropped without holding the lock.
+        drop::<Vec<_>>(removed);
+        self.inner.notify.notify_waiters();
+    }
+    /// Returns true if the pool has been closed
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+    /// Retrieve status of the pool. `Status::available` is the number of
+    /// free borrower slots of the existing objects minus the number of
+    /// waiting `get` calls and `Status::used_weight` the number of
+    /// handles.
+    pub fn status(&self) -> Status {
+        let state = self.inner.state.lock().unwrap();
+        let borrowers = state
+            .entries
+            .iter()
+            .map(|entry| entry.borrowers)
+            .sum::<usize>();
+        let slots = state.entries.len() * self.inner.max_borrowers();
+        Status {
+            max_size: self.inner.config.max_size,
+            size: state.entries.len() + state.pending,
+            available: (slots - borrowers) as isize - state.waiting as isize,
+            poisoned: 0,
+            used_weight: borrowers,
+            waiting: Waiting {
+                normal: state.waiting,
+                ..Waiting::default()
+            },
+            overflow: 0,
+        }
+    }
+    /// Get manager of the pool
+    pub fn manager(&self) -> &M {
+        &self.inner.manager
+    }
+}
@@ -0,0 +1,41 @@
+//! Observers which are notified about the events of a pool
+use std::time::Instant;
+
+/// A closure which is called with every event of a pool
+type Observer<E> = Box<dyn Fn(&E) + Send + Sync>;
+
+/// The observers registered with a pool. `E` is the event type of the
+/// pool.
+pub(crate) struct Observers<E> {
+    observers: Vec<Observer<E>>,
+}
+
+impl<E> Default for Observers<E> {
+    fn default() -> Self {
+        Self {
+            observers: Vec::new(),
+        }
+    }
+}
+
+impl<E> Observers<E> {
+    /// Register an observer
+    pub(crate) fn push(&mut self, observer: impl Fn(&E) + Send + Sync + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+    /// Returns the current time if any observers are registered. This is
+    /// used to measure the duration of `get` calls only if needed.
+    pub(crate) fn start(&self) -> Option<Instant> {
+        if self.observers.is_empty() {
+            None
+        } else {
+            Some(Instant::now())
+        }
+    }
+    /// Report `event` to all observers
+    pub(crate) fn notify(&self, event: E) {
+        for observer in &self.observers {
+            observer(&event);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Sharded queue of idle objects
+//!
+//! Every thread pushes to and pops from its own shard first so that
+//! threads returning and retrieving objects at the same time rarely
+//! contend for the same lock. Operations which need a consistent view of
+//! all idle objects lock every shard using `IdleQueue::lock_all`.
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Upper bound of the number of shards of a queue
+const MAX_SHARDS: usize = 64;
+
+static NEXT_THREAD_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Index of the current thread used to pick its shard
+    static THREAD_INDEX: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+fn thread_index() -> usize {
+    THREAD_INDEX.with(|index| match index.get() {
+        Some(index) => index,
+        None => {
+            let new_index = NEXT_THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+            index.set(Some(new_index));
+            new_index
+        }
+    })
+}
+
+struct Entry<T> {
+    item: T,
+    /// Sequence number of the push. Used to find the least and most
+    /// recently returned items across shards. A counter is used instead
+    /// of a timestamp because it is incremented next to `IdleQueue::len`
+    /// which every push modifies anyway, while reading the clock would
+    /// add to every push.
+    pushed_at: u64,
+}
+
+/// A shard padded to a cache line so that locking one shard doesn't
+/// invalidate the cache line of its neighbour.
+#[repr(align(64))]
+struct Shard<T>(Mutex<Vec<Entry<T>>>);
+
+/// A queue of idle objects split into multiple shards. The number of
+/// items is tracked while holding the lock of the modified shard so
+/// `IdleQueue::len` never disagrees with the contents of the shards.
+///
+/// If worker affinity is enabled every thread additionally owns a stash
+/// of up to `stash_size` items which is tried before the shared shards.
+/// Threads whose stash and the shared shards are empty steal items from
+/// the stashes of other threads.
+pub(crate) struct IdleQueue<T> {
+    /// The shared shards followed by the stashes
+    shards: Box<[Shard<T>]>,
+    /// Number of shared shards
+    shared: usize,
+    /// Maximum number of items per stash or zero if worker affinity is
+    /// disabled
+    stash_size: usize,
+    len: AtomicUsize,
+    /// Sequence number of the next push
+    next_push: AtomicU64,
+}
+
+/// Get the number of shards for the given number of items. The number
+/// depends on the number of CPUs and is always a power of two.
+fn shard_count(items: usize) -> usize {
+    num_cpus::get()
+        .min(items)
+        .max(1)
+        .next_power_of_two()
+        .min(MAX_SHARDS)
+}
+
+impl<T> IdleQueue<T> {
+    /// Create a queue for up to `capacity` items. The number of shards
+    /// depends on the number of CPUs and never exceeds `capacity`.
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self::with_stashes(capacity, 0)
+    }
+    /// Create a queue for up to `capacity` items which keeps up to
+    /// `stash_size` items per thread in a stash. A `stash_size` of zero
+    /// disables the stashes.
+    pub(crate) fn with_stashes(capacity: usize, stash_size: usize) -> Self {
+        let shared = shard_count(capacity);
+        let stashes = if stash_size > 0 {
+            shard_count(usize::MAX)
+        } else {
+            0
+        };
+        let per_shard = capacity.div_ceil(shared);
+        Self {
+            shards: (0..shared)
+                .map(|_| Shard(Mutex::new(Vec::with_capacity(per_shard))))
+                .chain((0..stashes).map(|_| Shard(Mutex::new(Vec::with_capacity(stash_size)))))
+                .collect(),
+            shared,
+            stash_size,
+            len: AtomicUsize::new(0),
+            next_push: AtomicU64::new(0),
+        }
+    }
+    /// Create a queue containing `items` which can hold at least
+    /// `capacity` items.
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn from_vec(items: Vec<T>, capacity: usize) -> Self {
+        let queue = Self::new(capacity.max(items.len()));
+        for item in items {
+            queue.push(item);
+        }
+        queue
+    }
+    /// Get the number of items in the queue
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+    /// Returns true if the queue is empty
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Number of stashes
+    fn stashes(&self) -> usize {
+        self.shards.len() - self.shared
+    }
+    /// The shared shard of the current thread
+    fn home(&self) -> usize {
+        // The number of shards is a power of two.
+        thread_index() & (self.shared - 1)
+    }
+    /// The stash of the current thread if worker affinity is enabled
+    fn home_stash(&self) -> Option<usize> {
+        match self.stashes() {
+            0 => None,
+            stashes => Some(self.shared + (thread_index() & (stashes - 1))),
+        }
+    }
+    fn lock(&self, index: usize) -> MutexGuard<'_, Vec<Entry<T>>> {
+        self.shards[index].0.lock().unwrap()
+    }
+    /// Add an item to the stash or shard of the current thread
+    pub(crate) fn push(&self, item: T) {
+        self.lock_home().push(item);
+    }
+    /// Lock the stash of the current thread or its shared shard if the
+    /// stash is full or worker affinity is disabled.
+    pub(crate) fn lock_home(&self) -> ShardGuard<'_, T> {
+        if let Some(stash) = self.home_stash() {
+            let shard = self.lock(stash);
+            if shard.len() < self.stash_size {
+                return ShardGuard {
+                    shard,
+                    len: &self.len,
+                    next_push: &self.next_push,
+                };
+            }
+        }
+        ShardGuard {
+            shard: self.lock(self.home()),
+            len: &self.len,
+            next_push: &self.next_push,
+        }
+    }
+    /// Remove the most recently pushed item of the stash or shard of the
+    /// current thread. If they are empty the other shared shards are
+    /// searched before stealing the least recently pushed item of the
+    /// stash of another thread. Returns `None` if the queue is empty.
+    pub(crate) fn pop(&self) -> Option<T> {
+        let home = self.home();
+        let home_stash = self.home_stash();
+        let shared = (0..self.shared).map(|i| (home + i) & (self.shared - 1));
+        let stashes = self.stashes();
+        let others = (1..stashes).map(|i| {
+            let offset = home_stash.unwrap() - self.shared;
+            self.shared + ((offset + i) & (stashes - 1))
+        });
+        // Items might move between shards which have already been
+        // searched and shards which haven't, so the search is repeated
+        // until an item is found or the queue is empty.
+        while !self.is_empty() {
+            for index in home_stash.into_iter().chain(shared.clone()) {
+                if let Some(entry) = self.lock(index).pop() {
+                    self.len.fetch_sub(1, Ordering::Release);
+                    return Some(entry.item);
+                }
+            }
+            for index in others.clone() {
+                let mut shard = self.lock(index);
+                if !shard.is_empty() {
+                    let entry = shard.remove(0);
+                    self.len.fetch_sub(1, Ordering::Release);
+                    return Some(entry.item);
+                }
+            }
+        }
+        None
+    }
+    /// Lock all shards. The shards are always locked in the same order
+    /// so concurrent calls can't deadlock.
+    pub(crate) fn lock_all(&self) -> QueueGuard<'_, T> {
+        QueueGuard {
+            shards: (0..self.shards.len()).map(|i| self.lock(i)).collect(),
+            len: &self.len,
+        }
+    }
+    /// Unwrap the items starting with the least recently pushed one
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        let mut entries = self
+            .shards
+            .into_vec()
+            .into_iter()
+            .flat_map(|shard| shard.0.into_inner().unwrap())
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.pushed_at);
+        entries.into_iter().map(|entry| entry.item).collect()
+    }
+}
+
+/// Exclusive access to the shard of the current thread
+pub(crate) struct ShardGuard<'a, T> {
+    shard: MutexGuard<'a, Vec<Entry<T>>>,
+    len: &'a AtomicUsize,
+    next_push: &'a AtomicU64,
+}
+
+impl<T> ShardGuard<'_, T> {
+    /// Add an item to the shard
+    pub(crate) fn push(&mut self, item: T) {
+        self.shard.push(Entry {
+            item,
+            pushed_at: self.next_push.fetch_add(1, Ordering::Relaxed),
+        });
+        self.len.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Position of an item returned by `QueueGuard::position_newest`
+#[cfg(feature = "unmanaged")]
+pub(crate) struct Position {
+    shard: usize,
+    index: usize,
+}
+
+/// Exclusive access to all shards of an `IdleQueue`
+pub(crate) struct QueueGuard<'a, T> {
+    shards: Vec<MutexGuard<'a, Vec<Entry<T>>>>,
+    len: &'a AtomicUsize,
+}
+
+impl<T> QueueGuard<'_, T> {
+    /// Get the number of items in the queue
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+    /// Remove the given entries of every shard keeping the others
+    fn remove_entries<F>(&mut self, mut f: F) -> Vec<Entry<T>>
+    where
+        F: FnMut(&Entry<T>) -> bool,
+    {
+        let mut removed = Vec::new();
+        for shard in self.shards.iter_mut() {
+            let (r, retained): (Vec<_>, Vec<_>) = shard.drain(..).partition(|entry| f(entry));
+            **shard = retained;
+            removed.extend(r);
+        }
+        self.len.fetch_sub(removed.len(), Ordering::Release);
+        removed
+    }
+    /// Remove all items for which `f` returns true
+    pub(crate) fn remove_if<F>(&mut self, mut f: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.remove_entries(|entry| f(&entry.item))
+            .into_iter()
+            .map(|entry| entry.item)
+            .collect()
+    }
+    /// Remove all items
+    pub(crate) fn take_all(&mut self) -> Vec<T> {
+        self.remove_if(|_| true)
+    }
+    /// Remove up to `n` items which have been pushed least recently
+    pub(crate) fn take_oldest(&mut self, n: usize) -> Vec<T> {
+        if n == 0 {
+            return Vec::new();
+        }
+        if n >= self.len() {
+            return self.take_all();
+        }
+        let limit = self.nth_pushed_at(n, false);
+        self.take_pushed(n, |pushed_at| pushed_at <= limit)
+    }
+    /// Remove up to `n` items which have been pushed most recently
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn take_newest(&mut self, n: usize) -> Vec<T> {
+        if n == 0 {
+            return Vec::new();
+        }
+        if n >= self.len() {
+            return self.take_all();
+        }
+        let limit = self.nth_pushed_at(n, true);
+        self.take_pushed(n, |pushed_at| pushed_at >= limit)
+    }
+    /// Get the sequence number of the `n`th oldest or newest item. `n`
+    /// must be greater than zero and less than the number of items.
+    fn nth_pushed_at(&self, n: usize, newest: bool) -> u64 {
+        let mut pushes = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.iter().map(|entry| entry.pushed_at))
+            .collect::<Vec<_>>();
+        let index = if newest { pushes.len() - n } else { n - 1 };
+        *pushes.select_nth_unstable(index).1
+    }
+    /// Remove up to `n` items whose sequence number matches `f`
+    fn take_pushed<F>(&mut self, n: usize, f: F) -> Vec<T>
+    where
+        F: Fn(u64) -> bool,
+    {
+        let mut remaining = n;
+        self.remove_entries(|entry| {
+            if remaining > 0 && f(entry.pushed_at) {
+                remaining -= 1;
+                true
+            } else {
+                false
+            }
+        })
+        .into_iter()
+        .map(|entry| entry.item)
+        .collect()
+    }
+    /// Find the most recently pushed item for which `f` returns true
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn position_newest<F>(&self, mut f: F) -> Option<Position>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut newest: Option<(u64, Position)> = None;
+        for (shard, entries) in self.shards.iter().enumerate() {
+            // Items are pushed to the end of their shard, so the last
+            // matching item of a shard is its newest one.
+            if let Some(index) = entries.iter().rposition(|entry| f(&entry.item)) {
+                let pushed_at = entries[index].pushed_at;
+                match &newest {
+                    Some((t, _)) if *t > pushed_at => {}
+                    _ => newest = Some((pushed_at, Position { shard, index })),
+                }
+            }
+        }
+        newest.map(|(_, position)| position)
+    }
+    /// Remove the item at `position`
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn remove(&mut self, position: Position) -> T {
+        let entry = self.shards[position.shard].remove(position.index);
+        self.len.fetch_sub(1, Ordering::Release);
+        entry.item
+    }
+}
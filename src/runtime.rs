@@ -2,11 +2,12 @@
 use std::future::Future;
 use std::time::Duration;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 /// Enumeration for picking a runtime implementation
 pub enum Runtime {
     /// Disable runtime specific features
     /// This disables timeouts and the possibility to spawn async tasks.
+    #[default]
     None,
     /// tokio 1.0 runtime
     #[cfg(feature = "rt_tokio_1")]
@@ -16,12 +17,6 @@ pub enum Runtime {
     AsyncStd1,
 }
 
-impl Default for Runtime {
-    fn default() -> Self {
-        Runtime::None
-    }
-}
-
 pub enum TimeoutError {
     Timeout,
     NoRuntime,
@@ -50,4 +45,168 @@ impl Runtime {
                 .map_err(|_| TimeoutError::Timeout),
         }
     }
+    /// Wait until `duration` has elapsed
+    #[cfg(feature = "managed")]
+    pub(crate) async fn sleep(&self, duration: Duration) -> Result<(), TimeoutError> {
+        match self.timeout(duration, std::future::pending::<()>()).await {
+            Err(TimeoutError::Timeout) => Ok(()),
+            Err(TimeoutError::NoRuntime) => Err(TimeoutError::NoRuntime),
+            Ok(()) => unreachable!(),
+        }
+    }
+    /// Spawn `future` as a new task. Returns false if no runtime is
+    /// specified.
+    #[cfg(feature = "managed")]
+    #[allow(unused_variables)]
+    pub(crate) fn spawn<F>(&self, future: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match self {
+            Self::None => false,
+            #[cfg(feature = "rt_tokio_1")]
+            Self::Tokio1 => {
+                tokio::spawn(future);
+                true
+            }
+            #[cfg(feature = "rt_async-std_1")]
+            Self::AsyncStd1 => {
+                async_std::task::spawn(future);
+                true
+            }
+        }
+    }
+}
+
+/// The context a pool was created in. Blocking get methods use it to
+/// spawn or drive futures which need the runtime, e.g. for timeouts.
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RuntimeContext {
+    /// Handle of the tokio runtime the pool was created in
+    #[cfg(feature = "rt_tokio_1")]
+    tokio: Option<tokio::runtime::Handle>,
+}
+
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+impl RuntimeContext {
+    /// Capture the context of the current thread
+    pub(crate) fn current() -> Self {
+        Self {
+            #[cfg(feature = "rt_tokio_1")]
+            tokio: tokio::runtime::Handle::try_current().ok(),
+        }
+    }
+    /// Run `future` to completion blocking the current thread. The
+    /// future is polled on the current thread which is parked while
+    /// waiting. For `Runtime::Tokio1` the runtime the pool was created
+    /// in is entered so that timers and IO resources can be used, and
+    /// `TimeoutError::NoRuntime` is returned if there is no such
+    /// runtime.
+    #[cfg(feature = "unmanaged")]
+    pub(crate) fn block_on<F: Future>(
+        &self,
+        runtime: &Runtime,
+        future: F,
+    ) -> Result<F::Output, TimeoutError> {
+        match runtime {
+            Runtime::None => Ok(park_on(future)),
+            #[cfg(feature = "rt_tokio_1")]
+            Runtime::Tokio1 => {
+                let handle = self.tokio.as_ref().ok_or(TimeoutError::NoRuntime)?;
+                let _guard = handle.enter();
+                Ok(park_on(future))
+            }
+            #[cfg(feature = "rt_async-std_1")]
+            Runtime::AsyncStd1 => Ok(async_std::task::block_on(future)),
+        }
+    }
+    /// Spawn `future` as a new task and block the current thread until
+    /// it completes. For `Runtime::Tokio1` the task is spawned on the
+    /// runtime the pool was created in, and `TimeoutError::NoRuntime` is
+    /// returned if there is no such runtime or it is shut down before
+    /// the task completes. Without a runtime the future is polled on the
+    /// current thread.
+    #[cfg(feature = "managed")]
+    pub(crate) fn spawn_and_wait<F>(
+        &self,
+        runtime: &Runtime,
+        future: F,
+    ) -> Result<F::Output, TimeoutError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match runtime {
+            Runtime::None => Ok(park_on(future)),
+            #[cfg(feature = "rt_tokio_1")]
+            Runtime::Tokio1 => {
+                let handle = self.tokio.as_ref().ok_or(TimeoutError::NoRuntime)?;
+                match park_on(handle.spawn(future)) {
+                    Ok(output) => Ok(output),
+                    Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                    Err(_) => Err(TimeoutError::NoRuntime),
+                }
+            }
+            #[cfg(feature = "rt_async-std_1")]
+            Runtime::AsyncStd1 => Ok(async_std::task::block_on(async_std::task::spawn(future))),
+        }
+    }
+}
+
+/// Run `f` which blocks the current thread. Returns `None` if the
+/// current thread is running an async runtime which must not be
+/// blocked. Only runtimes enabled via the `rt_*` features are detected.
+///
+/// Threads of a multi-threaded tokio runtime run `f` using
+/// `tokio::task::block_in_place` so that the other tasks of a worker
+/// thread are moved to another thread while it is blocked. This also
+/// covers threads of the blocking thread pool and `Runtime::block_on`.
+/// A current-thread tokio runtime can't hand off its tasks and is
+/// therefore rejected. This includes its blocking thread pool as it
+/// can't be told apart from the runtime thread.
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+pub(crate) fn block_in_place<R>(f: impl FnOnce() -> R) -> Option<R> {
+    #[cfg(feature = "rt_tokio_1")]
+    {
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            return match handle.runtime_flavor() {
+                tokio::runtime::RuntimeFlavor::MultiThread => Some(tokio::task::block_in_place(f)),
+                _ => None,
+            };
+        }
+    }
+    #[cfg(feature = "rt_async-std_1")]
+    {
+        if async_std::task::try_current().is_some() {
+            return None;
+        }
+    }
+    Some(f())
+}
+
+/// Wakes up a thread parked by `park_on`
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+struct ThreadWaker(std::thread::Thread);
+
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: std::sync::Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` on the current thread parking it until the future is
+/// woken up.
+#[cfg(any(feature = "managed", feature = "unmanaged"))]
+fn park_on<F: Future>(future: F) -> F::Output {
+    let waker = std::sync::Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            std::task::Poll::Ready(output) => return output,
+            std::task::Poll::Pending => std::thread::park(),
+        }
+    }
 }
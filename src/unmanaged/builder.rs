@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{Event, Pool, PoolConfig, Validator};
+use crate::leak::LeakDetector;
+use crate::observer::Observers;
+use crate::{BuildError, Runtime};
+
+/// Builder for unmanaged pools. A builder is created via `Pool::builder`.
+///
+/// Unlike `Pool::from_config` the configuration is validated when calling
+/// `PoolBuilder::build` so that invalid combinations are reported upfront
+/// rather than on every call to `Pool::get`.
+pub struct PoolBuilder<T> {
+    config: PoolConfig,
+    validate: Option<Validator<T>>,
+    observers: Observers<Event>,
+    leak_threshold: Option<Duration>,
+}
+
+impl<T> PoolBuilder<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            config: PoolConfig::default(),
+            validate: None,
+            observers: Observers::default(),
+            leak_threshold: None,
+        }
+    }
+    /// Replace the whole pool configuration
+    pub fn config(mut self, config: PoolConfig) -> Self {
+        self.config = config;
+        self
+    }
+    /// Set the maximum size of the pool
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.config.max_size = max_size;
+        self
+    }
+    /// Set the timeout for `Pool::get`
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+    /// Set the runtime used for timeouts
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.config.runtime = runtime;
+        self
+    }
+    /// Set the closure which is called for every object before it is
+    /// handed out by `Pool::get`. Objects for which it returns false are
+    /// removed from the pool.
+    pub fn validate(mut self, f: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.validate = Some(Arc::new(f));
+        self
+    }
+    /// Add an observer which is called for every `Event` of the pool,
+    /// e.g. for collecting metrics. Observers are called synchronously
+    /// by the task causing the event and should return quickly.
+    pub fn observer(mut self, observer: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.observers.push(observer);
+        self
+    }
+    /// Enable leak detection. The caller location of every `Pool::get`
+    /// is recorded and a warning is logged when an object is returned
+    /// after being checked out for longer than `threshold`. Objects which
+    /// are still checked out after `threshold` are reported once when a
+    /// `get` has to wait for an object and, if a runtime is configured,
+    /// periodically while it keeps waiting. The current holders can be
+    /// listed using `Pool::checked_out`.
+    pub fn leak_detection(mut self, threshold: Duration) -> Self {
+        self.leak_threshold = Some(threshold);
+        self
+    }
+    /// Validate the configuration and create the pool
+    pub fn build(self) -> Result<Pool<T>, BuildError> {
+        if self.config.max_size == 0 {
+            return Err(BuildError::ZeroMaxSize);
+        }
+        // A timeout of zero is handled without a runtime.
+        if matches!(self.config.runtime, Runtime::None)
+            && matches!(self.config.timeout, Some(t) if t.as_nanos() > 0)
+        {
+            return Err(BuildError::NoRuntimeSpecified("get"));
+        }
+        Ok(Pool::from_parts(
+            &self.config,
+            self.validate,
+            self.leak_threshold.map(LeakDetector::new),
+            self.observers,
+        ))
+    }
+}
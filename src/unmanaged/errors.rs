@@ -9,6 +9,8 @@ pub enum PoolError {
     Closed,
     /// No runtime specified
     NoRuntimeSpecified,
+    /// A blocking get method was called from within an async runtime
+    BlockingInAsyncContext,
 }
 
 impl fmt::Display for PoolError {
@@ -20,6 +22,10 @@ impl fmt::Display for PoolError {
             ),
             Self::Closed => write!(f, "The pool has been closed."),
             Self::NoRuntimeSpecified => write!(f, "No runtime specified."),
+            Self::BlockingInAsyncContext => write!(
+                f,
+                "Blocking get methods must not be called from within an async runtime."
+            ),
         }
     }
 }
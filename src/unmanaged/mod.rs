@@ -27,20 +27,31 @@
 //! }
 //! ```
 
+use std::convert::{TryFrom, TryInto};
+use std::future::Future;
 use std::ops::{Deref, DerefMut};
+use std::panic::Location;
 use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
-use std::{convert::TryInto, time::Duration};
+use std::time::{Duration, Instant};
 
-use tokio::sync::{Semaphore, TryAcquireError};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{Notify, Semaphore, TryAcquireError};
 
-use crate::runtime::TimeoutError;
-pub use crate::Status;
+use crate::leak::{self, LeakDetector};
+use crate::observer::Observers;
+use crate::queue::IdleQueue;
+use crate::runtime::{block_in_place, RuntimeContext, TimeoutError};
+pub use crate::{BuildError, CheckedOut, Metrics, Status, Waiting};
 
+mod builder;
+pub use self::builder::PoolBuilder;
 mod config;
 pub use self::config::PoolConfig;
 mod errors;
 pub use self::errors::PoolError;
+mod observer;
+pub use self::observer::Event;
 
 /// A wrapper around the actual pooled object which implements the traits
 /// `Deref`, `DerefMut` and `Drop`. Use this object just as if it was of type
@@ -49,6 +60,10 @@ pub use self::errors::PoolError;
 pub struct Object<T> {
     obj: Option<T>,
     pool: Weak<PoolInner<T>>,
+    /// Id of the checkout if leak detection is enabled
+    checkout: Option<u64>,
+    metrics: Metrics,
+    expires_at: Option<Instant>,
 }
 
 impl<T> Object<T> {
@@ -57,24 +72,36 @@ impl<T> Object<T> {
     /// using the `Pool::add` or `Pool::try_add` methods.
     pub fn take(mut this: Self) -> T {
         if let Some(pool) = this.pool.upgrade() {
-            pool.size.fetch_sub(1, Ordering::Relaxed);
+            pool.checkin(this.checkout.take());
+            let mut drain = pool.drain.lock().unwrap();
+            if pool.size.fetch_sub(1, Ordering::Relaxed) == 1 {
+                // This was the last object of a drained pool
+                *drain = None;
+            }
             pool.size_semaphore.add_permits(1);
         }
         this.obj.take().unwrap()
     }
+    /// Get the metrics of this object
+    pub fn metrics(this: &Self) -> &Metrics {
+        &this.metrics
+    }
 }
 
 impl<T> Drop for Object<T> {
     fn drop(&mut self) {
         if let Some(obj) = self.obj.take() {
             if let Some(pool) = self.pool.upgrade() {
-                {
-                    let mut queue = pool.queue.lock().unwrap();
-                    queue.push(obj);
-                }
+                pool.checkin(self.checkout.take());
+                pool.observers.notify(Event::Return);
+                self.metrics.recycle();
                 pool.available.fetch_add(1, Ordering::Relaxed);
-                pool.semaphore.add_permits(1);
-                pool.clean_up();
+                pool.push(ObjectInner {
+                    obj,
+                    metrics: self.metrics,
+                    expires_at: self.expires_at,
+                });
+                pool.notify.notify_waiters();
             }
         }
     }
@@ -105,9 +132,22 @@ impl<T> AsMut<T> for Object<T> {
     }
 }
 
+/// A closure which is used to validate objects before they are handed
+/// out by the pool. See `PoolBuilder::validate`.
+pub type Validator<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// An idle object stored in the queue of the pool
+struct ObjectInner<T> {
+    obj: T,
+    metrics: Metrics,
+    /// Set by `Pool::add_with_ttl`
+    expires_at: Option<Instant>,
+}
+
 struct PoolInner<T> {
     config: PoolConfig,
-    queue: Mutex<Vec<T>>,
+    validate: Option<Validator<T>>,
+    queue: IdleQueue<ObjectInner<T>>,
     size: AtomicUsize,
     /// This semaphore has as many permits as `max_size - size`. Every time
     /// an object is added to the pool a permit is removed from the semaphore
@@ -118,6 +158,16 @@ struct PoolInner<T> {
     /// number of futures waiting for an object.
     available: AtomicIsize,
     semaphore: Semaphore,
+    /// Wakes up `get_matching` calls whenever an object is added or
+    /// returned to the pool.
+    notify: Notify,
+    /// Set by `Pool::drain`. Objects which are returned to a drained pool
+    /// are sent to the `Drain` instead of being added to the queue.
+    drain: Mutex<Option<UnboundedSender<T>>>,
+    leak_detector: Option<LeakDetector>,
+    /// The context the pool was created in. Used by `Pool::blocking_get`.
+    context: RuntimeContext,
+    observers: Observers<Event>,
 }
 
 /// A generic object and connection pool. This is the static version of the
@@ -156,91 +206,300 @@ impl<T> Pool<T> {
     pub fn new(max_size: usize) -> Self {
         Self::from_config(&PoolConfig::new(max_size))
     }
+    /// Create a `PoolBuilder` for a new empty pool. Unlike
+    /// `Pool::from_config` the builder validates the configuration
+    /// before creating the pool.
+    pub fn builder() -> PoolBuilder<T> {
+        PoolBuilder::new()
+    }
     /// Create a new empty pool using the given configuration
     pub fn from_config(config: &PoolConfig) -> Self {
+        Self::from_parts(config, None, None, Observers::default())
+    }
+    /// Create a new pool using the given configuration containing the
+    /// objects of `iter`. If `iter` yields more than `max_size` objects a
+    /// tuple containing the objects and `PoolError::Timeout` is returned
+    /// instead.
+    pub fn from_iter_with_config<I>(
+        iter: I,
+        config: &PoolConfig,
+    ) -> Result<Self, (Vec<T>, PoolError)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let pool = Self::from_config(config);
+        pool.try_add_many(iter)?;
+        Ok(pool)
+    }
+    fn from_parts(
+        config: &PoolConfig,
+        validate: Option<Validator<T>>,
+        leak_detector: Option<LeakDetector>,
+        observers: Observers<Event>,
+    ) -> Self {
         Self {
             inner: Arc::new(PoolInner {
                 config: config.clone(),
-                queue: Mutex::new(Vec::with_capacity(config.max_size)),
+                validate,
+                queue: IdleQueue::new(config.max_size),
                 size: AtomicUsize::new(0),
                 size_semaphore: Semaphore::new(config.max_size),
                 available: AtomicIsize::new(0),
                 semaphore: Semaphore::new(0),
+                notify: Notify::new(),
+                drain: Mutex::new(None),
+                leak_detector,
+                context: RuntimeContext::current(),
+                observers,
             }),
         }
     }
     /// Retrieve object from pool or wait for one to become available.
-    pub async fn get(&self) -> Result<Object<T>, PoolError> {
-        self.timeout_get(self.inner.config.timeout).await
+    #[track_caller]
+    pub fn get(&self) -> impl Future<Output = Result<Object<T>, PoolError>> + '_ {
+        self.timeout_get_at(self.inner.config.timeout, Location::caller())
     }
     /// Retrieve object from the pool and do not wait if there is currently
     /// no object available and the maximum pool size has been reached.
+    #[track_caller]
     pub fn try_get(&self) -> Result<Object<T>, PoolError> {
-        let inner = self.inner.as_ref();
-        let permit = inner.semaphore.try_acquire().map_err(|e| match e {
-            TryAcquireError::NoPermits => PoolError::Timeout,
-            TryAcquireError::Closed => PoolError::Closed,
-        })?;
-        let obj = {
-            let mut queue = inner.queue.lock().unwrap();
-            queue.pop().unwrap()
-        };
-        permit.forget();
-        inner.available.fetch_sub(1, Ordering::Relaxed);
-        Ok(Object {
-            pool: Arc::downgrade(&self.inner),
-            obj: Some(obj),
-        })
+        let location = Location::caller();
+        let started = self.inner.observers.start();
+        let result = self.inner.try_pop().map(|obj| self.checkout(obj, location));
+        self.inner.observe_get(&result, 1, started);
+        result
     }
     /// Retrieve object using a different timeout config than the one
     /// configured.
-    pub async fn timeout_get(&self, timeout: Option<Duration>) -> Result<Object<T>, PoolError> {
+    #[track_caller]
+    pub fn timeout_get(
+        &self,
+        timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<Object<T>, PoolError>> + '_ {
+        self.timeout_get_at(timeout, Location::caller())
+    }
+    /// Retrieve object from pool blocking the current thread until one
+    /// becomes available. This makes it possible to use the pool from
+    /// synchronous code without an async runtime. If the pool is
+    /// configured to use `Runtime::Tokio1` the runtime the pool was
+    /// created in is used for the timeout.
+    ///
+    /// This method can be called from the worker threads and the
+    /// blocking thread pool of a multi-threaded tokio runtime. It uses
+    /// `tokio::task::block_in_place` so that other tasks keep running.
+    /// Calling it from within a current-thread tokio runtime or an
+    /// async-std task fails with `PoolError::BlockingInAsyncContext` as
+    /// it would block the runtime. Only runtimes enabled via the
+    /// `rt_tokio_1` and `rt_async-std_1` features can be detected.
+    #[track_caller]
+    pub fn blocking_get(&self) -> Result<Object<T>, PoolError> {
+        self.blocking_timeout_get_at(self.inner.config.timeout, Location::caller())
+    }
+    /// Blocking version of `Pool::timeout_get`. See `Pool::blocking_get`
+    /// for details.
+    #[track_caller]
+    pub fn blocking_timeout_get(&self, timeout: Option<Duration>) -> Result<Object<T>, PoolError> {
+        self.blocking_timeout_get_at(timeout, Location::caller())
+    }
+    fn blocking_timeout_get_at(
+        &self,
+        timeout: Option<Duration>,
+        location: &'static Location<'static>,
+    ) -> Result<Object<T>, PoolError> {
+        block_in_place(|| {
+            self.inner.context.block_on(
+                &self.inner.config.runtime,
+                self.timeout_get_at(timeout, location),
+            )
+        })
+        .ok_or(PoolError::BlockingInAsyncContext)?
+        .map_err(|_| PoolError::NoRuntimeSpecified)?
+    }
+    /// Retrieve object using the given timeout recording `location` as
+    /// the caller if leak detection is enabled.
+    async fn timeout_get_at(
+        &self,
+        timeout: Option<Duration>,
+        location: &'static Location<'static>,
+    ) -> Result<Object<T>, PoolError> {
+        let started = self.inner.observers.start();
+        let result = self.pop_at(timeout).await;
+        let result = result.map(|obj| self.checkout(obj, location));
+        self.inner.observe_get(&result, 1, started);
+        result
+    }
+    /// Wait for an idle object using the given timeout
+    async fn pop_at(&self, timeout: Option<Duration>) -> Result<ObjectInner<T>, PoolError> {
+        let inner = self.inner.as_ref();
+        match timeout {
+            Some(timeout) if timeout.as_nanos() == 0 => inner.try_pop(),
+            Some(timeout) => match inner
+                .config
+                .runtime
+                .timeout(timeout, inner.watch(inner.pop()))
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => Err(match e {
+                    TimeoutError::NoRuntime => PoolError::NoRuntimeSpecified,
+                    TimeoutError::Timeout => PoolError::Timeout,
+                }),
+            },
+            None => inner.watch(inner.pop()).await,
+        }
+    }
+    /// Retrieve `n` objects at once. The permits for all of them are
+    /// acquired atomically so that tasks needing multiple objects can't
+    /// deadlock each other. Requesting more than `max_size` objects fails
+    /// with `PoolError::Timeout`.
+    #[track_caller]
+    pub fn get_many(
+        &self,
+        n: usize,
+    ) -> impl Future<Output = Result<Vec<Object<T>>, PoolError>> + '_ {
+        self.get_many_at(n, Location::caller())
+    }
+    async fn get_many_at(
+        &self,
+        n: usize,
+        location: &'static Location<'static>,
+    ) -> Result<Vec<Object<T>>, PoolError> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let started = self.inner.observers.start();
+        let result = self.pop_many_at(n).await.map(|objs| {
+            objs.into_iter()
+                .map(|obj| self.checkout(obj, location))
+                .collect()
+        });
+        self.inner.observe_get(&result, n, started);
+        result
+    }
+    /// Wait for `n` idle objects using the configured timeout
+    async fn pop_many_at(&self, n: usize) -> Result<Vec<ObjectInner<T>>, PoolError> {
         let inner = self.inner.as_ref();
-        let permit = match timeout {
+        let permits = match u32::try_from(n) {
+            Ok(permits) if n <= inner.config.max_size => permits,
+            _ => return Err(PoolError::Timeout),
+        };
+        match inner.config.timeout {
+            Some(timeout) if timeout.as_nanos() == 0 => inner.try_pop_many(permits),
+            Some(timeout) => match inner
+                .config
+                .runtime
+                .timeout(timeout, inner.watch(inner.pop_many(permits)))
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => Err(match e {
+                    TimeoutError::NoRuntime => PoolError::NoRuntimeSpecified,
+                    TimeoutError::Timeout => PoolError::Timeout,
+                }),
+            },
+            None => inner.watch(inner.pop_many(permits)).await,
+        }
+    }
+    /// Retrieve an object for which `f` returns true or wait for one to
+    /// become available. Objects are only handed out if they are not
+    /// already reserved by a waiting `get` call so those calls are never
+    /// starved by calls to this method.
+    #[track_caller]
+    pub fn get_matching<'a, F>(
+        &'a self,
+        f: F,
+    ) -> impl Future<Output = Result<Object<T>, PoolError>> + 'a
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        self.timeout_get_matching_at(self.inner.config.timeout, f, Location::caller())
+    }
+    /// Retrieve an object for which `f` returns true using a different
+    /// timeout config than the one configured.
+    #[track_caller]
+    pub fn timeout_get_matching<'a, F>(
+        &'a self,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> impl Future<Output = Result<Object<T>, PoolError>> + 'a
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        self.timeout_get_matching_at(timeout, f, Location::caller())
+    }
+    async fn timeout_get_matching_at<F>(
+        &self,
+        timeout: Option<Duration>,
+        mut f: F,
+        location: &'static Location<'static>,
+    ) -> Result<Object<T>, PoolError>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let started = self.inner.observers.start();
+        let result = self.pop_matching_at(timeout, &mut f).await;
+        let result = result.map(|obj| self.checkout(obj, location));
+        self.inner.observe_get(&result, 1, started);
+        result
+    }
+    /// Wait for an idle object matching `f` using the given timeout
+    async fn pop_matching_at<F>(
+        &self,
+        timeout: Option<Duration>,
+        f: &mut F,
+    ) -> Result<ObjectInner<T>, PoolError>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let inner = self.inner.as_ref();
+        match timeout {
             Some(timeout) if timeout.as_nanos() == 0 => {
-                inner.semaphore.try_acquire().map_err(|e| match e {
-                    TryAcquireError::NoPermits => PoolError::Timeout,
-                    TryAcquireError::Closed => PoolError::Closed,
-                })
+                inner.try_pop_matching(f)?.ok_or(PoolError::Timeout)
             }
             Some(timeout) => match inner
                 .config
                 .runtime
-                .timeout(timeout, inner.semaphore.acquire())
+                .timeout(timeout, inner.watch(inner.pop_matching(f)))
                 .await
             {
-                Ok(result) => result.map_err(|_| PoolError::Closed),
+                Ok(result) => result,
                 Err(e) => Err(match e {
                     TimeoutError::NoRuntime => PoolError::NoRuntimeSpecified,
                     TimeoutError::Timeout => PoolError::Timeout,
                 }),
             },
-            None => inner
-                .semaphore
-                .acquire()
-                .await
-                .map_err(|_| PoolError::Closed),
-        }?;
-        let obj = {
-            let mut queue = inner.queue.lock().unwrap();
-            queue.pop().unwrap()
-        };
-        permit.forget();
-        inner.available.fetch_sub(1, Ordering::Relaxed);
-        Ok(Object {
+            None => inner.watch(inner.pop_matching(f)).await,
+        }
+    }
+    /// Wrap an object which has been removed from the queue
+    fn checkout(&self, obj: ObjectInner<T>, location: &'static Location<'static>) -> Object<T> {
+        self.inner.available.fetch_sub(1, Ordering::Relaxed);
+        Object {
             pool: Arc::downgrade(&self.inner),
-            obj: Some(obj),
-        })
+            obj: Some(obj.obj),
+            checkout: self.inner.checkout(location),
+            metrics: obj.metrics,
+            expires_at: obj.expires_at,
+        }
     }
     /// Add object to pool. If the `size` has already reached `max_size`
     /// this function blocks until the object can be added to the pool.
     /// If the pool has been closed a tuple containing the object and
     /// the error is returned instead.
     pub async fn add(&self, obj: T) -> Result<(), (T, PoolError)> {
+        self.add_until(obj, None).await
+    }
+    /// Add object to pool which is discarded once `ttl` has elapsed. The
+    /// object is not removed while it is checked out but it is no longer
+    /// handed out by `Pool::get` afterwards. See `Pool::add` for details.
+    pub async fn add_with_ttl(&self, obj: T, ttl: Duration) -> Result<(), (T, PoolError)> {
+        self.add_until(obj, Some(Instant::now() + ttl)).await
+    }
+    async fn add_until(&self, obj: T, expires_at: Option<Instant>) -> Result<(), (T, PoolError)> {
         match self.inner.size_semaphore.acquire().await {
             Ok(permit) => {
                 permit.forget();
-                self._add(obj);
+                self._add(obj, expires_at);
                 Ok(())
             }
             Err(_) => Err((obj, PoolError::Closed)),
@@ -253,7 +512,7 @@ impl<T> Pool<T> {
         match self.inner.size_semaphore.try_acquire() {
             Ok(permit) => {
                 permit.forget();
-                self._add(obj);
+                self._add(obj, None);
                 Ok(())
             }
             Err(e) => Err(match e {
@@ -262,18 +521,92 @@ impl<T> Pool<T> {
             }),
         }
     }
+    /// Add multiple objects to the pool at once. If adding them would
+    /// exceed `max_size` this function blocks until all of them can be
+    /// added. Either all or none of the objects are added. If the pool has
+    /// been closed or more than `max_size` objects are given a tuple
+    /// containing the objects and the error is returned instead.
+    pub async fn add_many<I>(&self, objs: I) -> Result<(), (Vec<T>, PoolError)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let objs = objs.into_iter().collect::<Vec<_>>();
+        let count = match self.permit_count(objs.len()) {
+            Some(count) => count,
+            None => return Err((objs, PoolError::Timeout)),
+        };
+        match self.inner.size_semaphore.acquire_many(count).await {
+            Ok(permit) => {
+                permit.forget();
+                self._add_many(objs);
+                Ok(())
+            }
+            Err(_) => Err((objs, PoolError::Closed)),
+        }
+    }
+    /// Try to add multiple objects to the pool at once. Either all or none
+    /// of the objects are added. If adding them would exceed `max_size` or
+    /// the pool has been closed a tuple containing the objects and the
+    /// cause of the error is returned instead.
+    pub fn try_add_many<I>(&self, objs: I) -> Result<(), (Vec<T>, PoolError)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let objs = objs.into_iter().collect::<Vec<_>>();
+        let count = match self.permit_count(objs.len()) {
+            Some(count) => count,
+            None => return Err((objs, PoolError::Timeout)),
+        };
+        match self.inner.size_semaphore.try_acquire_many(count) {
+            Ok(permit) => {
+                permit.forget();
+                self._add_many(objs);
+                Ok(())
+            }
+            Err(e) => Err(match e {
+                TryAcquireError::NoPermits => (objs, PoolError::Timeout),
+                TryAcquireError::Closed => (objs, PoolError::Closed),
+            }),
+        }
+    }
+    /// Get the number of `size_semaphore` permits needed for adding `len`
+    /// objects. Returns `None` if they can never be added.
+    fn permit_count(&self, len: usize) -> Option<u32> {
+        if len > self.inner.config.max_size {
+            return None;
+        }
+        u32::try_from(len).ok()
+    }
     /// Internal function which adds an object to the pool. Prior calling
     /// this it must be guaranteed that `size` does not exceed `max_size`.
     /// In the methods `add` and `try_add` this is ensured by using the
     /// `size_semaphore`.
-    fn _add(&self, obj: T) {
+    fn _add(&self, obj: T, expires_at: Option<Instant>) {
         self.inner.size.fetch_add(1, Ordering::Relaxed);
-        {
-            let mut queue = self.inner.queue.lock().unwrap();
-            queue.push(obj);
-        }
         self.inner.available.fetch_add(1, Ordering::Relaxed);
-        self.inner.semaphore.add_permits(1);
+        self.inner.push(ObjectInner {
+            obj,
+            metrics: Metrics::new(),
+            expires_at,
+        });
+        self.inner.notify.notify_waiters();
+    }
+    /// Internal function which adds multiple objects to the pool. See
+    /// `Pool::_add` for details.
+    fn _add_many(&self, objs: Vec<T>) {
+        let len = objs.len();
+        self.inner.size.fetch_add(len, Ordering::Relaxed);
+        self.inner
+            .available
+            .fetch_add(len as isize, Ordering::Relaxed);
+        for obj in objs {
+            self.inner.push(ObjectInner {
+                obj,
+                metrics: Metrics::new(),
+                expires_at: None,
+            });
+        }
+        self.inner.notify.notify_waiters();
     }
     /// Remove an object from the pool. This is a shortcut for
     /// ```rust,ignore
@@ -301,19 +634,81 @@ impl<T> Pool<T> {
     pub async fn tiemout_remove(&self, timeout: Option<Duration>) -> Result<T, PoolError> {
         self.timeout_get(timeout).await.map(Object::take)
     }
+    /// Remove all idle objects for which `f` returns false. The objects
+    /// which are currently checked out are not affected. Returns the
+    /// number of removed objects.
+    pub fn retain(&self, mut f: impl FnMut(&T, Metrics) -> bool) -> usize {
+        let inner = self.inner.as_ref();
+        let removed = {
+            let mut queue = inner.queue.lock_all();
+            let removed = queue.remove_if(|obj| {
+                // Objects whose permit has already been acquired by a
+                // `get` call are about to be handed out and must be
+                // kept in the queue.
+                !f(&obj.obj, obj.metrics)
+                    && inner
+                        .semaphore
+                        .try_acquire()
+                        .map(|permit| permit.forget())
+                        .is_ok()
+            });
+            inner.size.fetch_sub(removed.len(), Ordering::Relaxed);
+            inner
+                .available
+                .fetch_sub(removed.len() as isize, Ordering::Relaxed);
+            inner.size_semaphore.add_permits(removed.len());
+            removed
+        };
+        // The removed objects are dropped without holding the lock.
+        removed.len()
+    }
     /// Close the pool
     ///
     /// All current and future tasks waiting for objects return
     /// `Err(PoolError::Closed)` immediately.
     pub fn close(&self) {
-        self.inner.semaphore.close();
-        self.inner.size_semaphore.close();
-        self.inner.clear();
+        // The removed objects are dropped without holding the lock.
+        drop(self.inner.close(None));
+    }
+    /// Close the pool and remove all of its objects. The idle objects are
+    /// returned right away while objects which are currently checked out
+    /// can be received using `Drain::next` once they are returned.
+    pub fn drain(&self) -> (Vec<T>, Drain<T>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let idle = self.inner.close(Some(tx));
+        (idle, Drain { rx })
+    }
+    /// Unwrap the objects of the pool. This only succeeds if this is the
+    /// only reference to the pool and no objects are checked out.
+    /// Otherwise the pool is returned as error.
+    pub fn into_inner(self) -> Result<Vec<T>, Self> {
+        if Arc::strong_count(&self.inner) != 1 || Arc::weak_count(&self.inner) != 0 {
+            return Err(self);
+        }
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => Ok(inner
+                .queue
+                .into_vec()
+                .into_iter()
+                .map(|obj| obj.obj)
+                .collect()),
+            Err(inner) => Err(Self { inner }),
+        }
     }
     /// Returns true if the pool has been closed
     pub fn is_closed(&self) -> bool {
         self.inner.is_closed()
     }
+    /// List the objects which are currently checked out starting with
+    /// the one which has been checked out the longest. The list is always
+    /// empty unless leak detection has been enabled via
+    /// `PoolBuilder::leak_detection`.
+    pub fn checked_out(&self) -> Vec<CheckedOut> {
+        match &self.inner.leak_detector {
+            Some(leak_detector) => leak_detector.checked_out(),
+            None => Vec::new(),
+        }
+    }
     /// Retrieve status of the pool
     pub fn status(&self) -> Status {
         let max_size = self.inner.config.max_size;
@@ -323,28 +718,256 @@ impl<T> Pool<T> {
             max_size,
             size,
             available,
+            poisoned: 0,
+            used_weight: size.saturating_sub(available.max(0) as usize),
+            waiting: Waiting::default(),
+            overflow: 0,
         }
     }
 }
 
+/// The objects of a drained pool which were checked out while calling
+/// `Pool::drain`. See `Drain::next`.
+pub struct Drain<T> {
+    rx: UnboundedReceiver<T>,
+}
+
+impl<T> Drain<T> {
+    /// Wait for the next object to be returned to the drained pool.
+    /// Returns `None` once all objects have been returned or taken from
+    /// the pool using `Object::take`.
+    pub async fn next(&mut self) -> Option<T> {
+        self.rx.recv().await
+    }
+}
+
 impl<T> PoolInner<T> {
-    /// Clean up internals of the pool.
-    ///
-    /// This method is called after closing the pool and whenever a
-    /// object is returned to the pool and makes sure closed pools
-    /// do not contain objects.
-    fn clean_up(&self) {
-        if self.is_closed() {
-            self.clear();
-        }
-    }
-    /// Remove all objects which are currently part of the pool.
-    fn clear(&self) {
-        let mut queue = self.queue.lock().unwrap();
-        self.size.fetch_sub(queue.len(), Ordering::Relaxed);
-        self.available
-            .fetch_sub(queue.len() as isize, Ordering::Relaxed);
-        queue.clear();
+    /// Close the pool and remove the idle objects. If `tx` is given the
+    /// objects which are currently checked out are sent to it once they
+    /// are returned.
+    fn close(&self, tx: Option<UnboundedSender<T>>) -> Vec<T> {
+        let idle = {
+            let mut queue = self.queue.lock_all();
+            // `Object::take` decrements `size` while holding this lock so
+            // the `Drain` is never set up after the last object is gone.
+            let mut drain = self.drain.lock().unwrap();
+            // Objects whose permit has already been acquired by a `get`
+            // call are left in the queue and handed out as usual.
+            let mut count = 0;
+            while let Ok(permit) = self.semaphore.try_acquire() {
+                permit.forget();
+                count += 1;
+            }
+            self.semaphore.close();
+            self.size_semaphore.close();
+            self.available.fetch_sub(count as isize, Ordering::Relaxed);
+            if self.size.fetch_sub(count, Ordering::Relaxed) > count {
+                *drain = tx;
+            }
+            queue.take_oldest(count)
+        };
+        self.notify.notify_waiters();
+        idle.into_iter().map(|obj| obj.obj).collect()
+    }
+    /// Add an idle object which is already counted by `available` and
+    /// make its permit available. Both happen while holding the lock of
+    /// the shard so that `PoolInner::close` never sees an object without
+    /// its permit. Objects returned to a closed pool are sent to the
+    /// `Drain` if the pool is being drained and dropped otherwise.
+    fn push(&self, obj: ObjectInner<T>) {
+        let mut shard = self.queue.lock_home();
+        if !self.is_closed() {
+            shard.push(obj);
+            self.semaphore.add_permits(1);
+            return;
+        }
+        drop(shard);
+        self.available.fetch_sub(1, Ordering::Relaxed);
+        let mut drain = self.drain.lock().unwrap();
+        if let Some(tx) = drain.as_ref() {
+            // The receiver might have been dropped in which case the
+            // object is dropped as well.
+            let _ = tx.send(obj.obj);
+        }
+        if self.size.fetch_sub(1, Ordering::Relaxed) == 1 {
+            *drain = None;
+        }
+    }
+    /// Remove an idle object from the queue without waiting. Expired and
+    /// invalid objects are discarded.
+    fn try_pop(&self) -> Result<ObjectInner<T>, PoolError> {
+        loop {
+            let permit = self.semaphore.try_acquire().map_err(|e| match e {
+                TryAcquireError::NoPermits => PoolError::Timeout,
+                TryAcquireError::Closed => PoolError::Closed,
+            })?;
+            permit.forget();
+            let obj = self.queue.pop().unwrap();
+            if let Some(obj) = self.validate(obj) {
+                return Ok(obj);
+            }
+        }
+    }
+    /// Wait for an idle object and remove it from the queue. Expired and
+    /// invalid objects are discarded.
+    async fn pop(&self) -> Result<ObjectInner<T>, PoolError> {
+        loop {
+            let permit = self
+                .semaphore
+                .acquire()
+                .await
+                .map_err(|_| PoolError::Closed)?;
+            permit.forget();
+            let obj = self.queue.pop().unwrap();
+            if let Some(obj) = self.validate(obj) {
+                return Ok(obj);
+            }
+        }
+    }
+    /// Remove `n` idle objects from the queue without waiting. Expired and
+    /// invalid objects are discarded.
+    fn try_pop_many(&self, n: u32) -> Result<Vec<ObjectInner<T>>, PoolError> {
+        loop {
+            let permit = self.semaphore.try_acquire_many(n).map_err(|e| match e {
+                TryAcquireError::NoPermits => PoolError::Timeout,
+                TryAcquireError::Closed => PoolError::Closed,
+            })?;
+            permit.forget();
+            if let Some(objs) = self.pop_acquired(n as usize) {
+                return Ok(objs);
+            }
+        }
+    }
+    /// Wait for `n` idle objects and remove them from the queue. Expired
+    /// and invalid objects are discarded.
+    async fn pop_many(&self, n: u32) -> Result<Vec<ObjectInner<T>>, PoolError> {
+        loop {
+            let permit = self
+                .semaphore
+                .acquire_many(n)
+                .await
+                .map_err(|_| PoolError::Closed)?;
+            permit.forget();
+            if let Some(objs) = self.pop_acquired(n as usize) {
+                return Ok(objs);
+            }
+        }
+    }
+    /// Remove `n` idle objects whose permits have already been acquired.
+    /// If any of them is discarded the others are put back and `None` is
+    /// returned so that the caller never holds on to some of the objects
+    /// while waiting for the rest.
+    fn pop_acquired(&self, n: usize) -> Option<Vec<ObjectInner<T>>> {
+        let objs = self.queue.lock_all().take_newest(n);
+        let objs = objs
+            .into_iter()
+            .filter_map(|obj| self.validate(obj))
+            .collect::<Vec<_>>();
+        if objs.len() == n {
+            return Some(objs);
+        }
+        for obj in objs {
+            self.push(obj);
+        }
+        self.notify.notify_waiters();
+        None
+    }
+    /// Check an object which has been removed from the queue. If it has
+    /// expired or is rejected by `PoolBuilder::validate` it is dropped and
+    /// its slot is freed.
+    fn validate(&self, obj: ObjectInner<T>) -> Option<ObjectInner<T>> {
+        let expired = matches!(obj.expires_at, Some(expires_at) if expires_at <= Instant::now());
+        let valid = !expired
+            && match &self.validate {
+                Some(validate) => validate(&obj.obj),
+                None => true,
+            };
+        if valid {
+            return Some(obj);
+        }
+        self.size.fetch_sub(1, Ordering::Relaxed);
+        self.available.fetch_sub(1, Ordering::Relaxed);
+        self.size_semaphore.add_permits(1);
+        None
+    }
+    /// Remove the most recently returned idle object for which `f` returns
+    /// true from the queue. Returns `None` if there is no such object or
+    /// all idle objects are reserved by `get` calls.
+    fn try_pop_matching<F>(&self, f: &mut F) -> Result<Option<ObjectInner<T>>, PoolError>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        loop {
+            let obj = {
+                let mut queue = self.queue.lock_all();
+                let position = match queue.position_newest(|obj| f(&obj.obj)) {
+                    Some(position) => position,
+                    None if self.is_closed() => return Err(PoolError::Closed),
+                    None => return Ok(None),
+                };
+                match self.semaphore.try_acquire() {
+                    Ok(permit) => {
+                        permit.forget();
+                        queue.remove(position)
+                    }
+                    Err(TryAcquireError::NoPermits) => return Ok(None),
+                    Err(TryAcquireError::Closed) => return Err(PoolError::Closed),
+                }
+            };
+            if let Some(obj) = self.validate(obj) {
+                return Ok(Some(obj));
+            }
+        }
+    }
+    /// Wait for an idle object for which `f` returns true and remove it
+    /// from the queue.
+    async fn pop_matching<F>(&self, f: &mut F) -> Result<ObjectInner<T>, PoolError>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        loop {
+            // Objects returned after this point wake up `notified` even
+            // though it is not polled yet.
+            let notified = self.notify.notified();
+            if let Some(obj) = self.try_pop_matching(f)? {
+                return Ok(obj);
+            }
+            notified.await;
+        }
+    }
+    /// Register a checkout with the leak detector
+    fn checkout(&self, location: &'static Location<'static>) -> Option<u64> {
+        self.leak_detector
+            .as_ref()
+            .map(|leak_detector| leak_detector.checkout(location))
+    }
+    /// Unregister a checkout from the leak detector
+    fn checkin(&self, checkout: Option<u64>) {
+        if let (Some(leak_detector), Some(id)) = (&self.leak_detector, checkout) {
+            leak_detector.checkin(id);
+        }
+    }
+    /// Wait for `future` reporting leaked objects in the meantime
+    async fn watch<F: Future>(&self, future: F) -> F::Output {
+        leak::watch(self.leak_detector.as_ref(), &self.config.runtime, future).await
+    }
+    /// Report the result of a `get` call for `n` objects to the
+    /// observers. `started` is returned by `Observers::start`.
+    fn observe_get<O>(&self, result: &Result<O, PoolError>, n: usize, started: Option<Instant>) {
+        let started = match started {
+            Some(started) => started,
+            None => return,
+        };
+        match result {
+            Ok(_) => {
+                let duration = started.elapsed();
+                for _ in 0..n {
+                    self.observers.notify(Event::Get { duration });
+                }
+            }
+            Err(PoolError::Timeout) => self.observers.notify(Event::Timeout),
+            Err(_) => {}
+        }
     }
     fn is_closed(&self) -> bool {
         matches!(
@@ -361,16 +984,29 @@ where
 {
     /// Create new pool from the given exact size iterator of objects.
     fn from(iter: I) -> Pool<T> {
-        let queue = iter.into_iter().collect::<Vec<_>>();
+        let queue = iter
+            .into_iter()
+            .map(|obj| ObjectInner {
+                obj,
+                metrics: Metrics::new(),
+                expires_at: None,
+            })
+            .collect::<Vec<_>>();
         let len = queue.len();
         Pool {
             inner: Arc::new(PoolInner {
-                queue: Mutex::new(queue),
+                queue: IdleQueue::from_vec(queue, len),
                 config: PoolConfig::new(len),
+                validate: None,
                 size: AtomicUsize::new(len),
                 size_semaphore: Semaphore::new(0),
                 available: AtomicIsize::new(len.try_into().unwrap()),
                 semaphore: Semaphore::new(len),
+                notify: Notify::new(),
+                drain: Mutex::new(None),
+                leak_detector: None,
+                context: RuntimeContext::current(),
+                observers: Observers::default(),
             }),
         }
     }
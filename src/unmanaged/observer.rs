@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+/// An event of an unmanaged pool. Events are reported to the observers
+/// registered via `PoolBuilder::observer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// An object was handed out by a `get` call
+    Get {
+        /// The time the `get` call took including waiting for the object
+        duration: Duration,
+    },
+    /// An object was returned to the pool
+    Return,
+    /// A `get` call failed because of a timeout
+    Timeout,
+}
@@ -6,7 +6,7 @@ mod tests {
     use async_trait::async_trait;
     use tokio::time::sleep;
 
-    use deadpool::managed::{Object, PoolError, RecycleResult};
+    use deadpool::managed::{Object, PoolError, Priority, RecycleResult, TimeoutType, Waiting};
     type Pool = deadpool::managed::Pool<Manager>;
 
     struct Manager {}
@@ -86,6 +86,25 @@ mod tests {
         assert_eq!(pool.status().available, 0);
     }
 
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_timeout_get() {
+        use deadpool::managed::{PoolConfig, Timeouts};
+        use deadpool::Runtime;
+
+        let mut config = PoolConfig::new(1);
+        config.runtime = Runtime::Tokio1;
+        let pool = Pool::from_config(Manager {}, config);
+        let obj = pool.get().await.unwrap();
+        // The pool itself waits forever; only the given timeout applies.
+        assert!(matches!(
+            pool.timeout_get(&Timeouts::wait_millis(10)).await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        drop(obj);
+        assert!(pool.timeout_get(&Timeouts::wait_millis(10)).await.is_ok());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_managed_concurrent() {
         let mgr = Manager {};
@@ -155,4 +174,273 @@ mod tests {
         assert_eq!(status.size, 2);
         assert_eq!(status.available, 2);
     }
+
+    #[tokio::test]
+    async fn test_managed_invalidate_all() {
+        let mgr = Manager {};
+        let pool = Pool::new(mgr, 2);
+        let mut obj0 = pool.get().await.unwrap();
+        let mut obj1 = pool.get().await.unwrap();
+        *obj0 = 1;
+        *obj1 = 1;
+        drop(obj0);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 1);
+
+        // The idle object is dropped immediately
+        pool.invalidate_all();
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 0);
+
+        // The checked out object is dropped when being returned
+        drop(obj1);
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+
+        let obj = pool.get().await.unwrap();
+        assert_eq!(*obj, 0);
+        drop(obj);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+    }
+
+    #[tokio::test]
+    async fn test_managed_retain() {
+        let mgr = Manager {};
+        let pool = Pool::new(mgr, 3);
+        let mut obj0 = pool.get().await.unwrap();
+        let mut obj1 = pool.get().await.unwrap();
+        let obj2 = pool.get().await.unwrap();
+        *obj0 = 1;
+        *obj1 = 2;
+        drop(obj0);
+        drop(obj1);
+        assert_eq!(pool.retain(|obj, _| *obj != 1), 1);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 1);
+
+        let obj1 = pool.get().await.unwrap();
+        assert_eq!(*obj1, 2);
+        let metrics = Object::metrics(&obj1);
+        assert_eq!(metrics.recycle_count, 1);
+        assert!(metrics.recycled.is_some());
+        assert_eq!(Object::metrics(&obj2).recycle_count, 0);
+        drop(obj1);
+        drop(obj2);
+        assert_eq!(pool.retain(|_, metrics| metrics.recycle_count > 0), 1);
+        // The freed slot can be used to create a new object
+        let _objs = (
+            pool.get().await.unwrap(),
+            pool.get().await.unwrap(),
+            pool.get().await.unwrap(),
+        );
+        assert_eq!(pool.status().size, 3);
+    }
+
+    #[tokio::test]
+    async fn test_managed_get_many() {
+        let mgr = Manager {};
+        let pool = Pool::new(mgr, 3);
+        let mut objs = pool.get_many(2).await.unwrap();
+        assert_eq!(objs.len(), 2);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 0);
+        assert!(matches!(
+            pool.get_many(4).await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        assert!(pool.get_many(0).await.unwrap().is_empty());
+
+        let get_many = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get_many(2).await.map(|objs| objs.len()) })
+        };
+        tokio::task::yield_now().await;
+        // Only one permit is available
+        assert!(!get_many.is_finished());
+        assert_eq!(pool.status().available, -2);
+        objs.pop();
+        assert_eq!(get_many.await.unwrap().unwrap(), 2);
+        drop(objs);
+        let status = pool.status();
+        assert_eq!(status.size, 3);
+        assert_eq!(status.available, 3);
+    }
+
+    #[tokio::test]
+    async fn test_managed_get_weighted() {
+        let mgr = Manager {};
+        let pool = Pool::new(mgr, 4);
+        let heavy = pool.get_weighted(3).await.unwrap();
+        let light = pool.get().await.unwrap();
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.used_weight, 4);
+        assert!(matches!(
+            pool.get_weighted(5).await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+
+        let get_weighted = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get_weighted(2).await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        drop(light);
+        tokio::task::yield_now().await;
+        // Only one slot has been freed so far
+        assert!(!get_weighted.is_finished());
+        drop(heavy);
+        get_weighted.await.unwrap().unwrap();
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 2);
+        assert_eq!(status.used_weight, 0);
+
+        let obj = pool.get_weighted(4).await.unwrap();
+        assert_eq!(pool.status().used_weight, 4);
+        Object::take(obj);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.used_weight, 0);
+        assert_eq!(pool.get_many(4).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_managed_get_with_priority() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .priority_aging(None)
+            .build()
+            .unwrap();
+        let obj = pool.get().await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            let pool = pool.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _obj = pool.get_with_priority(priority).await.unwrap();
+                tx.send(priority).unwrap();
+            });
+            tokio::task::yield_now().await;
+        }
+        let waiting = pool.status().waiting;
+        assert_eq!((waiting.low, waiting.normal, waiting.high), (1, 1, 1));
+        drop(obj);
+        assert_eq!(rx.recv().await, Some(Priority::High));
+        assert_eq!(rx.recv().await, Some(Priority::Normal));
+        assert_eq!(rx.recv().await, Some(Priority::Low));
+        assert_eq!(pool.status().waiting, Waiting::default());
+    }
+
+    #[tokio::test]
+    async fn test_managed_get_with_priority_aging() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .priority_aging(Some(Duration::from_millis(10)))
+            .build()
+            .unwrap();
+        let obj = pool.get().await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let low = {
+            let pool = pool.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _obj = pool.get_with_priority(Priority::Low).await.unwrap();
+                tx.send(Priority::Low).unwrap();
+            })
+        };
+        sleep(Duration::from_millis(50)).await;
+        let high = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let _obj = pool.get_with_priority(Priority::High).await.unwrap();
+                tx.send(Priority::High).unwrap();
+            })
+        };
+        tokio::task::yield_now().await;
+        drop(obj);
+        // The low priority caller has been waiting long enough to overtake
+        assert_eq!(rx.recv().await, Some(Priority::Low));
+        assert_eq!(rx.recv().await, Some(Priority::High));
+        low.await.unwrap();
+        high.await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_managed_get_with_priority_concurrent() {
+        let pool = Pool::new(Manager {}, 2);
+        let priorities = [Priority::Low, Priority::Normal, Priority::High];
+        let futures = (0..300)
+            .map(|i| {
+                let pool = pool.clone();
+                let priority = priorities[i % 3];
+                tokio::spawn(async move {
+                    let _obj = pool.get_with_priority(priority).await.unwrap();
+                    tokio::task::yield_now().await;
+                })
+            })
+            .collect::<Vec<_>>();
+        // Permits given back while callers start waiting must not get lost
+        tokio::time::timeout(Duration::from_secs(10), async {
+            for future in futures {
+                future.await.unwrap();
+            }
+        })
+        .await
+        .unwrap();
+        let status = pool.status();
+        assert_eq!(status.available, 2);
+        assert_eq!(status.waiting, Waiting::default());
+    }
+
+    #[tokio::test]
+    async fn test_managed_get_for() {
+        let pool = Pool::builder(Manager {})
+            .max_size(3)
+            .consumer_quota(2)
+            .consumer_quota_for("batch", 1)
+            .build()
+            .unwrap();
+        let a1 = pool.get_for("a").await.unwrap();
+        let a2 = pool.get_for("a").await.unwrap();
+        assert!(matches!(
+            pool.get_for("a").await,
+            Err(PoolError::QuotaExceeded)
+        ));
+        let batch = pool.get_for("batch").await.unwrap();
+        assert!(matches!(
+            pool.get_for("batch").await,
+            Err(PoolError::QuotaExceeded)
+        ));
+        drop(batch);
+        drop(a2);
+
+        // "a" already holds an object so "b" is served first even though
+        // "a" started waiting earlier.
+        let c1 = pool.get().await.unwrap();
+        let _c2 = pool.get().await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for consumer in ["a", "b"] {
+            let pool = pool.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _obj = pool.get_for(consumer).await.unwrap();
+                tx.send(consumer).unwrap();
+            });
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(pool.status().waiting.normal, 2);
+        drop(c1);
+        assert_eq!(rx.recv().await, Some("b"));
+        assert_eq!(rx.recv().await, Some("a"));
+        drop(a1);
+        assert_eq!(pool.status().waiting.normal, 0);
+    }
 }
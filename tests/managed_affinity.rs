@@ -0,0 +1,71 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::RecycleResult;
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    #[derive(Default)]
+    struct Manager {
+        created: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(self.created.fetch_add(1, Ordering::SeqCst))
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_worker_affinity() {
+        let pool = Pool::builder(Manager::default())
+            .max_size(4)
+            .worker_affinity(1)
+            .build()
+            .unwrap();
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.get().await.unwrap();
+        let obj2 = pool.get().await.unwrap();
+        // The first returned object is kept in the stash of this thread
+        // while the others are added to the shared queue.
+        drop(obj0);
+        drop(obj1);
+        drop(obj2);
+        let status = pool.status();
+        assert_eq!(status.size, 3);
+        assert_eq!(status.available, 3);
+        let obj = pool.get().await.unwrap();
+        assert_eq!(*obj, 0);
+        assert_eq!(pool.status().available, 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_managed_worker_affinity_stealing() {
+        let pool = Pool::builder(Manager::default())
+            .max_size(1)
+            .worker_affinity(1)
+            .build()
+            .unwrap();
+        let obj = pool.get().await.unwrap();
+        // Return the object on a different thread so that it ends up in
+        // the stash of that thread.
+        std::thread::spawn(move || drop(obj)).join().unwrap();
+        assert_eq!(pool.status().available, 1);
+        let obj = pool.try_get().await.unwrap();
+        assert_eq!(*obj, 0);
+        assert_eq!(pool.manager().created.load(Ordering::SeqCst), 1);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 0);
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::time::sleep;
+
+    use deadpool::managed::{AutoscaleConfig, RecycleResult};
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {}
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(0)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_autoscale() {
+        let pool = Pool::builder(Manager {})
+            .max_size(4)
+            .autoscale(AutoscaleConfig {
+                min_size: 1,
+                window: Duration::from_millis(20),
+                grow_threshold: Duration::from_millis(1),
+                step: 1,
+            })
+            .build()
+            .unwrap();
+        assert_eq!(pool.limit(), 1);
+
+        // A caller waiting longer than the threshold grows the pool
+        let obj = pool.get().await.unwrap();
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        sleep(Duration::from_millis(30)).await;
+        drop(obj);
+        waiting.await.unwrap().unwrap();
+        assert_eq!(pool.limit(), 2);
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.try_get().await.unwrap();
+        assert_eq!(pool.status().size, 2);
+        drop(obj0);
+        drop(obj1);
+
+        // Unused slots shrink the pool and drop the surplus idle objects
+        sleep(Duration::from_millis(30)).await;
+        let _obj = pool.get().await.unwrap();
+        assert_eq!(pool.limit(), 1);
+        assert_eq!(pool.status().size, 1);
+        assert!(pool.try_get().await.is_err());
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_autoscale_while_waiting() {
+        let pool = Pool::builder(Manager {})
+            .max_size(2)
+            .runtime(deadpool::Runtime::Tokio1)
+            .autoscale(AutoscaleConfig {
+                min_size: 1,
+                window: Duration::from_millis(20),
+                grow_threshold: Duration::from_millis(1),
+                step: 1,
+            })
+            .build()
+            .unwrap();
+
+        // A caller which is still waiting grows the pool without a wait
+        // timeout and without the object being returned.
+        let _obj = pool.get().await.unwrap();
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        tokio::time::timeout(Duration::from_millis(500), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.limit(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_managed_autoscale_on_enqueue() {
+        let pool = Pool::builder(Manager {})
+            .max_size(2)
+            .autoscale(AutoscaleConfig {
+                min_size: 1,
+                window: Duration::from_millis(20),
+                grow_threshold: Duration::from_millis(1),
+                step: 1,
+            })
+            .build()
+            .unwrap();
+
+        // Without a runtime the pending caller is taken into account
+        // when the next caller starts waiting.
+        let _obj = pool.get().await.unwrap();
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        sleep(Duration::from_millis(30)).await;
+        assert_eq!(pool.limit(), 1);
+        assert!(pool.try_get().await.is_err());
+        assert_eq!(pool.limit(), 2);
+        waiting.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::RecycleResult;
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    #[derive(Default)]
+    struct Manager {
+        created: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(self.created.fetch_add(1, Ordering::SeqCst))
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_managed_blocking_get() {
+        let pool = Pool::new(Manager::default(), 1);
+        let obj = pool.blocking_get().unwrap();
+        assert_eq!(*obj, 0);
+        // The waiting thread is woken up when the object is returned.
+        let waiting = {
+            let pool = pool.clone();
+            thread::spawn(move || *pool.blocking_get().unwrap())
+        };
+        while pool.status().available >= 0 {
+            thread::yield_now();
+        }
+        drop(obj);
+        assert_eq!(waiting.join().unwrap(), 0);
+        assert_eq!(pool.manager().created.load(Ordering::SeqCst), 1);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_blocking_get_in_async_context() {
+        use deadpool::managed::PoolError;
+
+        let pool = Pool::new(Manager::default(), 1);
+        assert!(matches!(
+            pool.blocking_get(),
+            Err(PoolError::BlockingInAsyncContext)
+        ));
+        assert_eq!(pool.status().available, 0);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[test]
+    fn test_managed_blocking_get_timeout() {
+        use deadpool::managed::{PoolConfig, PoolError, TimeoutType, Timeouts};
+        use deadpool::Runtime;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut config = PoolConfig::new(1);
+        config.runtime = Runtime::Tokio1;
+        config.timeouts = Timeouts::wait_millis(10);
+        // The pool uses the runtime it was created in for timeouts.
+        let pool = {
+            let _guard = runtime.enter();
+            Pool::from_config(Manager::default(), config)
+        };
+        let obj = pool.blocking_get().unwrap();
+        assert!(matches!(
+            pool.blocking_get(),
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        drop(obj);
+        let obj = pool
+            .blocking_timeout_get(&Timeouts::wait_millis(10))
+            .unwrap();
+        assert_eq!(*obj, 0);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[test]
+    fn test_managed_blocking_timeout_get_uses_given_wait() {
+        use deadpool::managed::{PoolConfig, PoolError, TimeoutType, Timeouts};
+        use deadpool::Runtime;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut config = PoolConfig::new(1);
+        config.runtime = Runtime::Tokio1;
+        let pool = {
+            let _guard = runtime.enter();
+            Pool::from_config(Manager::default(), config)
+        };
+        let _obj = pool.blocking_get().unwrap();
+        // The pool itself waits forever; only the given timeout applies.
+        assert!(matches!(
+            pool.blocking_timeout_get(&Timeouts::wait_millis(10)),
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[test]
+    fn test_managed_blocking_get_creates_on_runtime() {
+        use deadpool::managed::PoolConfig;
+        use deadpool::Runtime;
+
+        struct ThreadManager;
+
+        #[async_trait]
+        impl deadpool::managed::Manager for ThreadManager {
+            type Type = thread::ThreadId;
+            type Error = Infallible;
+            async fn create(&self) -> Result<thread::ThreadId, Infallible> {
+                Ok(thread::current().id())
+            }
+            async fn recycle(&self, _conn: &mut thread::ThreadId) -> RecycleResult<Infallible> {
+                Ok(())
+            }
+        }
+
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        let mut config = PoolConfig::new(1);
+        config.runtime = Runtime::Tokio1;
+        let pool = {
+            let _guard = runtime.enter();
+            deadpool::managed::Pool::<ThreadManager>::from_config(ThreadManager, config)
+        };
+        let obj = pool.blocking_get().unwrap();
+        assert_ne!(*obj, thread::current().id());
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_managed_blocking_get_in_spawn_blocking() {
+        let pool = Pool::new(Manager::default(), 1);
+        let obj = {
+            let pool = pool.clone();
+            tokio::task::spawn_blocking(move || pool.blocking_get().map(|obj| *obj))
+                .await
+                .unwrap()
+        };
+        assert_eq!(obj.unwrap(), 0);
+        assert_eq!(pool.status().available, 1);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_managed_blocking_get_in_multi_thread_runtime() {
+        let pool = Pool::new(Manager::default(), 1);
+        let obj = pool.blocking_get().unwrap();
+        // The waiting task blocks its worker thread while this task
+        // keeps running on another one.
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { *pool.blocking_get().unwrap() })
+        };
+        while pool.status().available >= 0 {
+            tokio::task::yield_now().await;
+        }
+        drop(obj);
+        assert_eq!(waiting.await.unwrap(), 0);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[test]
+    fn test_managed_blocking_get_without_runtime_handle() {
+        use deadpool::managed::{PoolConfig, PoolError, Timeouts};
+        use deadpool::Runtime;
+
+        let mut config = PoolConfig::new(1);
+        config.runtime = Runtime::Tokio1;
+        config.timeouts = Timeouts::wait_millis(10);
+        let pool = Pool::from_config(Manager::default(), config);
+        assert!(matches!(
+            pool.blocking_get(),
+            Err(PoolError::NoRuntimeSpecified)
+        ));
+    }
+}
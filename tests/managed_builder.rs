@@ -0,0 +1,255 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::{BuildError, HookError, PoolError, RecycleResult};
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {}
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(0)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_builder_zero_max_size() {
+        let result = Pool::builder(Manager {}).max_size(0).build();
+        assert!(matches!(result, Err(BuildError::ZeroMaxSize)));
+    }
+
+    #[test]
+    fn test_builder_zero_max_concurrent_creates() {
+        let result = Pool::builder(Manager {}).max_concurrent_creates(0).build();
+        assert!(matches!(result, Err(BuildError::ZeroMaxConcurrentCreates)));
+    }
+
+    #[tokio::test]
+    async fn test_builder_observer() {
+        use deadpool::managed::{Event, TimeoutType};
+        use std::sync::Arc;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let pool = {
+            let events = events.clone();
+            Pool::builder(Manager {})
+                .max_size(1)
+                .observer(move |event| events.lock().unwrap().push(*event))
+                .build()
+                .unwrap()
+        };
+        let obj = pool.get().await.unwrap();
+        assert!(matches!(
+            pool.try_get().await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        drop(obj);
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], Event::Create);
+        assert!(matches!(events[1], Event::Get { .. }));
+        assert_eq!(events[2], Event::Timeout(TimeoutType::Wait));
+        assert_eq!(events[3], Event::Return);
+    }
+
+    #[test]
+    fn test_builder_no_runtime() {
+        let result = Pool::builder(Manager {})
+            .wait_timeout(Some(Duration::from_millis(1)))
+            .build();
+        assert!(matches!(
+            result,
+            Err(BuildError::NoRuntimeSpecified("wait"))
+        ));
+        let result = Pool::builder(Manager {})
+            .create_timeout(Some(Duration::from_millis(1)))
+            .build();
+        assert!(matches!(
+            result,
+            Err(BuildError::NoRuntimeSpecified("create"))
+        ));
+        // A wait timeout of zero does not need a runtime
+        let result = Pool::builder(Manager {})
+            .wait_timeout(Some(Duration::from_millis(0)))
+            .build();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_builder_hooks() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .post_create(|obj| {
+                *obj += 1;
+                Ok(())
+            })
+            .post_recycle(|obj| {
+                *obj += 10;
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        assert_eq!(pool.status().max_size, 1);
+        assert_eq!(*pool.get().await.unwrap(), 1);
+        assert_eq!(*pool.get().await.unwrap(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_builder_post_create_hook_error() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .post_create(|_| Err(HookError::Message("fail".into())))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            pool.get().await,
+            Err(PoolError::PostCreateHook(HookError::Message(_)))
+        ));
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+    }
+
+    #[tokio::test]
+    async fn test_builder_post_recycle_hook_error() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .post_recycle(|_| Err(HookError::Message("fail".into())))
+            .build()
+            .unwrap();
+        let mut obj = pool.get().await.unwrap();
+        *obj = 42;
+        drop(obj);
+        // The recycled object is rejected and a new one is created
+        assert_eq!(*pool.get().await.unwrap(), 0);
+        assert_eq!(pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_builder_leak_detection() {
+        let pool = Pool::builder(Manager {})
+            .max_size(2)
+            .leak_detection(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert!(pool.checked_out().is_empty());
+        let line = line!();
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.try_get().await.unwrap();
+        let checked_out = pool.checked_out();
+        assert_eq!(checked_out.len(), 2);
+        assert_eq!(checked_out[0].location.file(), file!());
+        assert_eq!(checked_out[0].location.line(), line + 1);
+        assert_eq!(checked_out[1].location.line(), line + 2);
+        drop(obj0);
+        assert_eq!(pool.checked_out().len(), 1);
+        drop(obj1);
+        assert!(pool.checked_out().is_empty());
+    }
+
+    struct Logger {
+        warnings: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for Logger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let warning = record.args().to_string();
+                self.warnings.lock().unwrap().push(warning);
+            }
+        }
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Logger = Logger {
+        warnings: Mutex::new(Vec::new()),
+    };
+
+    #[tokio::test]
+    async fn test_builder_leak_detection_never_returned() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Warn);
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .leak_detection(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        let location = format!("{}:{}:", file!(), line!() + 1);
+        let obj = pool.get().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let reported = || {
+            LOGGER
+                .warnings
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|warning| warning.contains(&location))
+                .count()
+        };
+        assert_eq!(reported(), 0);
+        // The pool uses no timeout so the `get` would wait forever
+        let result = tokio::time::timeout(Duration::from_millis(20), pool.get()).await;
+        assert!(result.is_err());
+        assert_eq!(reported(), 1);
+        // Every checkout is only reported once
+        let result = tokio::time::timeout(Duration::from_millis(20), pool.get()).await;
+        assert!(result.is_err());
+        assert_eq!(reported(), 1);
+        drop(obj);
+    }
+
+    #[tokio::test]
+    async fn test_builder_get_many_hook_error() {
+        let pool = Pool::builder(Manager {})
+            .max_size(2)
+            .post_create(|obj| {
+                *obj += 1;
+                Ok(())
+            })
+            .post_recycle(|_| Err(HookError::Message("fail".into())))
+            .build()
+            .unwrap();
+        drop(pool.get().await.unwrap());
+        // The idle object can't be recycled and the new object is created
+        assert_eq!(
+            pool.get_many(2)
+                .await
+                .unwrap()
+                .iter()
+                .map(|obj| **obj)
+                .collect::<Vec<_>>(),
+            vec![1, 1]
+        );
+        let pool = Pool::builder(Manager {})
+            .max_size(2)
+            .post_create(|_| Err(HookError::Message("fail".into())))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            pool.get_many(2).await,
+            Err(PoolError::PostCreateHook(_))
+        ));
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+        assert!(matches!(
+            pool.get_many(2).await,
+            Err(PoolError::PostCreateHook(_))
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+    use tokio::time::sleep;
+
+    use deadpool::managed::RecycleResult;
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    #[derive(Default)]
+    struct Manager {
+        creating: AtomicUsize,
+        max_creating: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            let creating = self.creating.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_creating.fetch_max(creating, Ordering::SeqCst);
+            sleep(Duration::from_millis(50)).await;
+            self.creating.fetch_sub(1, Ordering::SeqCst);
+            Ok(0)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_max_concurrent_creates() {
+        let pool = Pool::builder(Manager::default())
+            .max_size(8)
+            .max_concurrent_creates(2)
+            .build()
+            .unwrap();
+        let handles = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let obj = pool.get().await.unwrap();
+                    sleep(Duration::from_millis(200)).await;
+                    drop(obj);
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(pool.manager().max_creating.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.status().size, 8);
+    }
+
+    #[tokio::test]
+    async fn test_managed_create_slot_returned_object() {
+        let pool = Pool::builder(Manager::default())
+            .max_size(4)
+            .max_concurrent_creates(1)
+            .build()
+            .unwrap();
+        let obj = pool.get().await.unwrap();
+        let creating = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let _obj = pool.get().await.unwrap();
+                Instant::now()
+            })
+        };
+        tokio::task::yield_now().await;
+        let returned = Instant::now();
+        drop(obj);
+        // The waiting caller takes the returned object instead of waiting
+        // for the create slot.
+        let received = waiting.await.unwrap();
+        assert!(received - returned < Duration::from_millis(40));
+        assert!(!creating.is_finished());
+        creating.await.unwrap().unwrap();
+        assert_eq!(pool.status().size, 2);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_create_rate() {
+        use deadpool::Runtime;
+
+        let pool = Pool::builder(Manager::default())
+            .max_size(4)
+            .create_rate(10.0, 2)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        let handles = (0..3)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let obj = pool.get().await.unwrap();
+                    sleep(Duration::from_millis(200)).await;
+                    drop(obj);
+                    start.elapsed()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut elapsed = Vec::new();
+        for handle in handles {
+            elapsed.push(handle.await.unwrap());
+        }
+        elapsed.sort();
+        // Two objects are created right away, the third one has to wait
+        // for the bucket to refill.
+        assert!(elapsed[1] < Duration::from_millis(300));
+        assert!(elapsed[2] >= Duration::from_millis(350));
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_create_slot_wait_budget() {
+        use deadpool::managed::{Object, PoolError, TimeoutType};
+        use deadpool::Runtime;
+        use tokio::sync::Semaphore;
+
+        /// Creates one object per permit of `gate`
+        struct GatedManager {
+            gate: Semaphore,
+        }
+
+        #[async_trait]
+        impl deadpool::managed::Manager for GatedManager {
+            type Type = ();
+            type Error = Infallible;
+            async fn create(&self) -> Result<(), Infallible> {
+                self.gate.acquire().await.unwrap().forget();
+                Ok(())
+            }
+            async fn recycle(&self, _conn: &mut ()) -> RecycleResult<Infallible> {
+                Ok(())
+            }
+        }
+
+        let pool = deadpool::managed::Pool::<GatedManager>::builder(GatedManager {
+            gate: Semaphore::new(1),
+        })
+        .max_size(2)
+        .max_concurrent_creates(1)
+        .wait_timeout(Some(Duration::from_millis(100)))
+        .runtime(Runtime::Tokio1)
+        .build()
+        .unwrap();
+        let obj = pool.get().await.unwrap();
+        // This caller holds the only create slot forever.
+        let creating = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        let start = Instant::now();
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        sleep(Duration::from_millis(70)).await;
+        Object::take(obj);
+        // The caller got a permit after 70ms and only has the rest of its
+        // wait timeout left for the create slot.
+        assert!(matches!(
+            waiting.await.unwrap(),
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        assert!(start.elapsed() < Duration::from_millis(150));
+        creating.abort();
+    }
+}
@@ -22,6 +22,7 @@ mod tests {
     #[derive(Clone)]
     struct RemoteControl {
         create_tx: Sender<Result<(), ()>>,
+        #[allow(dead_code)]
         recycle_tx: Sender<Result<(), ()>>,
     }
 
@@ -0,0 +1,154 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::time::timeout;
+
+    use deadpool::managed::{KeyedPool, RecycleResult};
+
+    struct Manager {}
+
+    #[async_trait]
+    impl deadpool::managed::KeyedManager<String> for Manager {
+        type Type = String;
+        type Error = Infallible;
+        async fn create(&self, key: &String) -> Result<String, Infallible> {
+            Ok(key.clone())
+        }
+        async fn recycle(&self, _key: &String, _conn: &mut String) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keyed_basic() {
+        let pool = KeyedPool::new(Manager {}, 4, 2);
+        let a = pool.get(&"a".to_string()).await.unwrap();
+        let b = pool.get(&"b".to_string()).await.unwrap();
+        assert_eq!(**a, "a");
+        assert_eq!(**b, "b");
+        let status = pool.status();
+        assert_eq!(status.max_size, 4);
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 0);
+        drop(a);
+        let status = pool.key_status(&"a".to_string()).unwrap();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+        assert!(pool.key_status(&"c".to_string()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_keyed_max_size_per_key() {
+        let pool = KeyedPool::new(Manager {}, 4, 1);
+        let key = "a".to_string();
+        let _a = pool.get(&key).await.unwrap();
+        assert!(timeout(Duration::from_millis(10), pool.get(&key))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keyed_global_max_size() {
+        let pool = KeyedPool::new(Manager {}, 2, 2);
+        let a = pool.get(&"a".to_string()).await.unwrap();
+        let _b = pool.get(&"b".to_string()).await.unwrap();
+        // The global limit is reached
+        let c = timeout(Duration::from_millis(10), pool.get(&"c".to_string())).await;
+        assert!(c.is_err());
+        // An idle object of another key is evicted to make room
+        drop(a);
+        let c = pool.get(&"c".to_string()).await.unwrap();
+        assert_eq!(**c, "c");
+        assert_eq!(pool.key_status(&"a".to_string()).unwrap().size, 0);
+        assert_eq!(pool.status().size, 2);
+    }
+
+    #[tokio::test]
+    async fn test_keyed_global_max_size_returned() {
+        let pool = KeyedPool::new(Manager {}, 1, 1);
+        let a = pool.get(&"a".to_string()).await.unwrap();
+        drop(a);
+        let b = timeout(Duration::from_secs(1), pool.get(&"b".to_string()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(**b, "b");
+        assert_eq!(pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyed_global_max_size_waiting() {
+        let pool = KeyedPool::new(Manager {}, 1, 1);
+        let a = pool.get(&"a".to_string()).await.unwrap();
+        let b = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get(&"b".to_string()).await.map(|b| (**b).clone()) })
+        };
+        tokio::task::yield_now().await;
+        // The object is returned while the creation for "b" is waiting
+        // for a global slot.
+        drop(a);
+        let b = timeout(Duration::from_secs(1), b).await.unwrap().unwrap();
+        assert_eq!(b.unwrap(), "b");
+        assert_eq!(pool.key_status(&"a".to_string()).unwrap().size, 0);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_keyed_global_max_size_wait_timeout() {
+        use deadpool::managed::{KeyedPoolConfig, PoolError, TimeoutType};
+        use deadpool::Runtime;
+
+        let mut config = KeyedPoolConfig::new(1, 1);
+        config.runtime = Runtime::Tokio1;
+        config.timeouts.wait = Some(Duration::from_millis(10));
+        let pool = KeyedPool::from_config(Manager {}, config);
+        let _a = pool.get(&"a".to_string()).await.unwrap();
+        // Waiting for a global slot is part of the wait timeout even
+        // without a create timeout.
+        let b = timeout(Duration::from_secs(1), pool.get(&"b".to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(b, Err(PoolError::Timeout(TimeoutType::Wait))));
+        assert_eq!(pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyed_global_max_size_same_key() {
+        let pool = KeyedPool::new(Manager {}, 1, 2);
+        let key = "a".to_string();
+        let a = pool.get(&key).await.unwrap();
+        let waiting = {
+            let pool = pool.clone();
+            let key = key.clone();
+            tokio::spawn(async move { pool.get(&key).await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        drop(a);
+        timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyed_evict_idle() {
+        let pool = KeyedPool::new(Manager {}, 4, 2);
+        let a = pool.get(&"a".to_string()).await.unwrap();
+        let b = pool.get(&"b".to_string()).await.unwrap();
+        drop(a);
+        assert_eq!(pool.evict_idle(Duration::from_secs(0)), 1);
+        assert!(pool.key_status(&"a".to_string()).is_none());
+        assert_eq!(pool.status().size, 1);
+        drop(b);
+        assert_eq!(pool.evict_idle(Duration::from_secs(3600)), 0);
+        assert_eq!(pool.evict_idle(Duration::from_secs(0)), 1);
+        assert_eq!(pool.status().size, 0);
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::{PoolError, RecycleResult, TimeoutType};
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {}
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(0)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_overflow() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .overflow(1, None)
+            .build()
+            .unwrap();
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.try_get().await.unwrap();
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.overflow, 1);
+        assert!(matches!(
+            pool.try_get().await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+
+        // Overflow objects are destroyed when returned
+        drop(obj1);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.overflow, 0);
+        drop(obj0);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_managed_overflow_after() {
+        use std::time::{Duration, Instant};
+
+        use deadpool::Runtime;
+
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .overflow(1, Some(Duration::from_millis(50)))
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        let _obj0 = pool.get().await.unwrap();
+        assert!(matches!(
+            pool.try_get().await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+        let start = Instant::now();
+        let obj1 = pool.get().await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(pool.status().overflow, 1);
+        drop(obj1);
+        assert_eq!(pool.status().overflow, 0);
+    }
+}
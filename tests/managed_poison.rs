@@ -0,0 +1,85 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::{Object, RecycleResult};
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {}
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            Ok(0)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poison_on_panic() {
+        let pool = Pool::builder(Manager {})
+            .max_size(1)
+            .poison_on_panic(true)
+            .build()
+            .unwrap();
+        let join_handle = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut obj = pool.get().await.unwrap();
+                *obj = 42;
+                panic!("task panicked while holding an object");
+            })
+        };
+        assert!(join_handle.await.is_err());
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+        assert_eq!(status.poisoned, 1);
+        assert_eq!(*pool.get().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_no_poison_on_panic() {
+        let pool = Pool::new(Manager {}, 1);
+        let join_handle = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut obj = pool.get().await.unwrap();
+                *obj = 42;
+                panic!("task panicked while holding an object");
+            })
+        };
+        assert!(join_handle.await.is_err());
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+        assert_eq!(status.poisoned, 0);
+        assert_eq!(*pool.get().await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_poison_object() {
+        let pool = Pool::new(Manager {}, 1);
+        let mut obj = pool.get().await.unwrap();
+        Object::poison(&mut obj);
+        assert!(Object::is_poisoned(&obj));
+        Object::clear_poison(&mut obj);
+        assert!(!Object::is_poisoned(&obj));
+        drop(obj);
+        assert_eq!(pool.status().size, 1);
+        let mut obj = pool.get().await.unwrap();
+        Object::poison(&mut obj);
+        drop(obj);
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+        assert_eq!(status.poisoned, 1);
+    }
+}
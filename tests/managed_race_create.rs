@@ -0,0 +1,105 @@
+#[cfg(all(feature = "managed", feature = "rt_tokio_1"))]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+    use tokio::time::sleep;
+
+    use deadpool::managed::{PoolError, RecycleResult};
+    use deadpool::Runtime;
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    #[derive(Default)]
+    struct Manager {
+        created: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            sleep(Duration::from_millis(200)).await;
+            Ok(self.created.fetch_add(1, Ordering::SeqCst))
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_race_creates() {
+        let pool = Pool::builder(Manager::default())
+            .max_size(2)
+            .race_creates(true)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        let obj = pool.get().await.unwrap();
+        assert_eq!(*obj, 0);
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let obj = pool.get().await.unwrap();
+                (*obj, Instant::now())
+            })
+        };
+        sleep(Duration::from_millis(50)).await;
+        let returned = Instant::now();
+        drop(obj);
+        // The waiting caller takes the returned object instead of waiting
+        // for the one which is being created.
+        let (value, received) = waiting.await.unwrap();
+        assert_eq!(value, 0);
+        assert!(received - returned < Duration::from_millis(40));
+
+        // The surplus object is added to the pool once it is created.
+        sleep(Duration::from_millis(250)).await;
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 2);
+        let obj0 = pool.try_get().await.unwrap();
+        let obj1 = pool.try_get().await.unwrap();
+        assert_eq!(*obj0 + *obj1, 1);
+    }
+
+    struct PanickingManager {}
+
+    #[async_trait]
+    impl deadpool::managed::Manager for PanickingManager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            panic!("create failed");
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_race_creates_panic() {
+        let pool = deadpool::managed::Pool::<PanickingManager>::builder(PanickingManager {})
+            .max_size(1)
+            .race_creates(true)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        assert!(matches!(pool.get().await, Err(PoolError::CreateAborted)));
+        // The slot of the failed creation is given back.
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+    }
+
+    #[test]
+    fn test_managed_race_creates_without_runtime() {
+        assert!(Pool::builder(Manager::default())
+            .race_creates(true)
+            .build()
+            .is_err());
+    }
+}
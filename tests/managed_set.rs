@@ -0,0 +1,121 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::{
+        AutoscaleConfig, PoolError, PoolSet, PoolSetConfig, RecycleResult, Strategy,
+    };
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {
+        id: usize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = ();
+        async fn create(&self) -> Result<usize, ()> {
+            if self.fail {
+                Err(())
+            } else {
+                Ok(self.id)
+            }
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<()> {
+            Ok(())
+        }
+    }
+
+    fn pool(id: usize, max_size: usize) -> Pool {
+        Pool::new(Manager { id, fail: false }, max_size)
+    }
+
+    #[tokio::test]
+    async fn test_set_round_robin() {
+        let set = PoolSet::new(vec![pool(0, 4), pool(1, 4)], Strategy::RoundRobin);
+        let ids = [
+            *set.get().await.unwrap(),
+            *set.get().await.unwrap(),
+            *set.get().await.unwrap(),
+            *set.get().await.unwrap(),
+        ];
+        assert_eq!(ids, [0, 1, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_set_least_in_use() {
+        let set = PoolSet::new(vec![pool(0, 4), pool(1, 4)], Strategy::LeastInUse);
+        let a = set.get().await.unwrap();
+        let b = set.get().await.unwrap();
+        assert_eq!([*a, *b], [0, 1]);
+        drop(a);
+        // The first pool has no objects in use anymore
+        let c = set.get().await.unwrap();
+        assert_eq!(*c, 0);
+        let _d = set.get().await.unwrap();
+        let status = set.status();
+        assert_eq!(status.max_size, 8);
+        assert_eq!(status.size, 3);
+        assert_eq!(status.available, 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_least_in_use_limit() {
+        let scaled = Pool::builder(Manager { id: 0, fail: false })
+            .max_size(8)
+            .autoscale(AutoscaleConfig::new(2))
+            .build()
+            .unwrap();
+        let set = PoolSet::new(vec![scaled, pool(1, 4)], Strategy::LeastInUse);
+        let a = set.get().await.unwrap();
+        let b = set.get().await.unwrap();
+        // The share of the first pool is relative to its limit of two
+        // objects rather than its `max_size` of eight.
+        let c = set.get().await.unwrap();
+        assert_eq!([*a, *b, *c], [0, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_set_empty() {
+        let set = PoolSet::new(Vec::<Pool>::new(), Strategy::LeastInUse);
+        assert!(matches!(set.get().await, Err(PoolError::NoPools)));
+    }
+
+    #[tokio::test]
+    async fn test_set_weighted() {
+        let set = PoolSet::new(vec![pool(0, 4), pool(1, 4)], Strategy::Weighted(vec![3, 1]));
+        let mut counts = [0, 0];
+        for _ in 0..8 {
+            counts[*set.get().await.unwrap()] += 1;
+        }
+        assert_eq!(counts, [6, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_set_skip_failing() {
+        let failing = Pool::new(Manager { id: 0, fail: true }, 4);
+        let mut config = PoolSetConfig::new(Strategy::RoundRobin);
+        config.max_failures = 1;
+        let set = PoolSet::from_config(vec![failing, pool(1, 4)], config);
+        // The failing pool is tried first and the set falls back to
+        // the other pool.
+        for _ in 0..4 {
+            assert_eq!(*set.get().await.unwrap(), 1);
+        }
+        assert_eq!(set.pools().next().unwrap().status().size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_all_failing() {
+        let set = PoolSet::new(
+            vec![Pool::new(Manager { id: 0, fail: true }, 4)],
+            Strategy::RoundRobin,
+        );
+        assert!(matches!(set.get().await, Err(PoolError::Backend(()))));
+        set.close();
+        assert!(matches!(set.get().await, Err(PoolError::Closed)));
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use tokio::sync::Semaphore;
+
+    use deadpool::managed::RecycleResult;
+    type Pool = deadpool::managed::Pool<Manager>;
+
+    struct Manager {
+        id: usize,
+        gate: Arc<Semaphore>,
+    }
+
+    impl Manager {
+        fn new(id: usize) -> Self {
+            Self::gated(id, Semaphore::MAX_PERMITS)
+        }
+        /// Create a manager which can only create `permits` objects
+        fn gated(id: usize, permits: usize) -> Self {
+            Self {
+                id,
+                gate: Arc::new(Semaphore::new(permits)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = Infallible;
+        async fn create(&self) -> Result<usize, Infallible> {
+            self.gate.acquire().await.unwrap().forget();
+            Ok(self.id)
+        }
+        async fn recycle(&self, _conn: &mut usize) -> RecycleResult<Infallible> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_manager_keep_objects() {
+        let pool = Pool::new(Manager::new(1), 2);
+        let obj = pool.get().await.unwrap();
+        assert_eq!(*obj, 1);
+        drop(obj);
+        pool.set_manager(Manager::new(2), false);
+        assert_eq!(pool.manager().id, 2);
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.get().await.unwrap();
+        assert_eq!(*obj0, 1);
+        assert_eq!(*obj1, 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_manager_invalidate() {
+        let pool = Pool::new(Manager::new(1), 2);
+        drop(pool.get().await.unwrap());
+        pool.set_manager(Manager::new(2), true);
+        assert_eq!(pool.status().size, 0);
+        assert_eq!(*pool.get().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_manager_in_flight_create() {
+        let manager = Manager::gated(1, 0);
+        let gate = manager.gate.clone();
+        let pool = Pool::new(manager, 2);
+        let join_handle = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|obj| *obj) })
+        };
+        tokio::task::yield_now().await;
+        pool.set_manager(Manager::new(2), true);
+        gate.add_permits(1);
+        // The create started with the old manager finishes but the object
+        // is discarded when it is returned.
+        assert_eq!(join_handle.await.unwrap().unwrap(), 1);
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+        assert_eq!(*pool.get().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_manager_waiting_get() {
+        let pool = Pool::new(Manager::new(1), 1);
+        let obj = pool.get().await.unwrap();
+        let join_handle = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|obj| *obj) })
+        };
+        tokio::task::yield_now().await;
+        pool.set_manager(Manager::new(2), true);
+        drop(obj);
+        // The waiting `get` uses the manager which is current once it
+        // got its permit.
+        assert_eq!(join_handle.await.unwrap().unwrap(), 2);
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(feature = "managed")]
+mod tests {
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use deadpool::managed::{PoolError, RecycleError, RecycleResult, TimeoutType};
+    type SharedPool = deadpool::managed::SharedPool<Manager>;
+
+    #[derive(Default)]
+    struct Manager {
+        created: AtomicUsize,
+        recycled: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl deadpool::managed::Manager for Manager {
+        type Type = usize;
+        type Error = ();
+        async fn create(&self) -> Result<usize, ()> {
+            Ok(self.created.fetch_add(1, Ordering::SeqCst))
+        }
+        async fn recycle(&self, conn: &mut usize) -> RecycleResult<()> {
+            self.recycled.fetch_add(1, Ordering::SeqCst);
+            // Object 1 can't be recycled.
+            match *conn {
+                1 => Err(RecycleError::Backend(())),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_shared() {
+        let pool = SharedPool::new(Manager::default(), 2, 2);
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.get().await.unwrap();
+        // The first object is shared before a second one is created.
+        assert_eq!((*obj0, *obj1), (0, 0));
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 0);
+        assert_eq!(status.used_weight, 2);
+
+        let obj2 = pool.get().await.unwrap();
+        let obj3 = pool.get().await.unwrap();
+        assert_eq!((*obj2, *obj3), (1, 1));
+        assert!(matches!(
+            pool.try_get().await,
+            Err(PoolError::Timeout(TimeoutType::Wait))
+        ));
+
+        // Dropping a handle frees a borrower slot.
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { *pool.get().await.unwrap() })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(pool.status().waiting.normal, 1);
+        drop(obj3);
+        assert_eq!(waiting.await.unwrap(), 1);
+        assert_eq!(pool.status().waiting.normal, 0);
+        assert_eq!(pool.manager().recycled.load(Ordering::SeqCst), 0);
+        drop(obj0);
+        drop(obj1);
+        drop(obj2);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 4);
+        assert_eq!(status.used_weight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_managed_shared_recycle() {
+        let pool = SharedPool::new(Manager::default(), 2, 1);
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.get().await.unwrap();
+        drop(obj0);
+        drop(obj1);
+        // Idle objects are recycled before they are handed out again.
+        // Object 1 fails to recycle and is replaced by object 2.
+        let obj0 = pool.get().await.unwrap();
+        let obj2 = pool.get().await.unwrap();
+        let mut objs = vec![*obj0, *obj2];
+        objs.sort();
+        assert_eq!(objs, vec![0, 2]);
+        assert_eq!(pool.manager().recycled.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.status().size, 2);
+    }
+
+    #[tokio::test]
+    async fn test_managed_shared_close() {
+        let pool = SharedPool::new(Manager::default(), 1, 1);
+        let obj = pool.get().await.unwrap();
+        let waiting = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        pool.close();
+        assert!(matches!(waiting.await.unwrap(), Err(PoolError::Closed)));
+        assert_eq!(pool.status().size, 1);
+        drop(obj);
+        assert_eq!(pool.status().size, 0);
+    }
+}
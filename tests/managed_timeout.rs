@@ -47,6 +47,12 @@ mod tests {
                 recycle: Some(Duration::from_millis(0)),
             },
             runtime,
+            overflow: 0,
+            overflow_after: None,
+            max_concurrent_creates: None,
+            create_rate: None,
+            create_burst: 1,
+            worker_stash: None,
         };
         let pool = Pool::from_config(mgr, cfg);
         assert!(matches!(pool.get().await, Err(PoolError::Timeout(_))));
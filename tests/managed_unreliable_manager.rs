@@ -43,19 +43,16 @@ mod tests {
         };
         let pool = Pool::new(manager, 16);
         {
-            assert_eq!(pool.get().await.is_ok(), false);
+            assert!(pool.get().await.is_err());
         }
         let status = pool.status();
         assert_eq!(status.available, 0);
         assert_eq!(status.size, 0);
         {
-            assert_eq!(
-                timeout(Duration::from_millis(10), pool.get())
-                    .await
-                    .unwrap()
-                    .is_ok(),
-                false
-            );
+            assert!(timeout(Duration::from_millis(10), pool.get())
+                .await
+                .unwrap()
+                .is_err());
         }
         assert_eq!(status.available, 0);
         assert_eq!(status.size, 0);
@@ -3,9 +3,9 @@ mod tests {
 
     use std::time::Duration;
 
-    use tokio::time::{interval, timeout};
+    use tokio::time::{interval, sleep, timeout};
 
-    use deadpool::unmanaged::{Pool, PoolError};
+    use deadpool::unmanaged::{BuildError, Object, Pool, PoolConfig, PoolError};
 
     #[tokio::test]
     async fn test_unmanaged_basic() {
@@ -158,4 +158,375 @@ mod tests {
         assert_eq!(pool.status().size, 1);
         assert_eq!(pool.try_remove().unwrap(), 2);
     }
+
+    #[test]
+    fn test_unmanaged_builder() {
+        assert!(matches!(
+            Pool::<()>::builder().max_size(0).build(),
+            Err(BuildError::ZeroMaxSize)
+        ));
+        assert!(matches!(
+            Pool::<()>::builder()
+                .timeout(Some(Duration::from_millis(1)))
+                .build(),
+            Err(BuildError::NoRuntimeSpecified(_))
+        ));
+        let pool = Pool::<()>::builder().max_size(2).build().unwrap();
+        assert_eq!(pool.status().max_size, 2);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_builder_observer() {
+        use deadpool::unmanaged::Event;
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let pool = {
+            let events = events.clone();
+            Pool::builder()
+                .max_size(1)
+                .observer(move |event| events.lock().unwrap().push(*event))
+                .build()
+                .unwrap()
+        };
+        pool.add(1).await.unwrap();
+        let obj = pool.get().await.unwrap();
+        assert!(matches!(pool.try_get(), Err(PoolError::Timeout)));
+        drop(obj);
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Event::Get { .. }));
+        assert_eq!(events[1], Event::Timeout);
+        assert_eq!(events[2], Event::Return);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_leak_detection() {
+        let pool = Pool::builder()
+            .max_size(2)
+            .leak_detection(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        pool.try_add(1).unwrap();
+        pool.try_add(2).unwrap();
+        let line = line!();
+        let obj = pool.get().await.unwrap();
+        let checked_out = pool.checked_out();
+        assert_eq!(checked_out.len(), 1);
+        assert_eq!(checked_out[0].location.file(), file!());
+        assert_eq!(checked_out[0].location.line(), line + 1);
+        Object::take(obj);
+        assert!(pool.checked_out().is_empty());
+        let line = line!();
+        let _obj = pool.try_get().unwrap();
+        assert_eq!(pool.checked_out()[0].location.line(), line + 1);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_retain() {
+        let pool = Pool::from(vec![1, 2, 3]);
+        let obj = pool.get().await.unwrap();
+        assert_eq!(Object::metrics(&obj).recycle_count, 0);
+        drop(obj);
+        assert_eq!(pool.retain(|obj, _| obj % 2 == 1), 1);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 2);
+        assert_eq!(pool.retain(|_, metrics| metrics.recycle_count == 0), 1);
+        let obj = pool.get().await.unwrap();
+        assert_eq!(*obj, 1);
+        assert!(pool.try_get().is_err());
+        // The removed objects free up space for new ones
+        pool.try_add(4).unwrap();
+        pool.try_add(5).unwrap();
+        assert!(pool.try_add(6).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_get_matching() {
+        let pool = Pool::from(vec![1, 2, 3]);
+        let obj = pool.get_matching(|obj| *obj == 2).await.unwrap();
+        assert_eq!(*obj, 2);
+        assert_eq!(pool.status().available, 2);
+        assert!(matches!(
+            pool.timeout_get_matching(Some(Duration::from_millis(0)), |obj| *obj == 2)
+                .await,
+            Err(PoolError::Timeout)
+        ));
+        let waiter = {
+            let pool = pool.clone();
+            tokio::spawn(async move { *pool.get_matching(|obj| *obj == 2).await.unwrap() })
+        };
+        tokio::task::yield_now().await;
+        drop(obj);
+        assert_eq!(waiter.await.unwrap(), 2);
+        assert_eq!(pool.status().available, 3);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_get_matching_no_starvation() {
+        let pool = Pool::from(vec![1]);
+        let obj = pool.get().await.unwrap();
+        let get = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get().await.unwrap() })
+        };
+        let get_matching = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get_matching(|_| true).await.map(|_| ()) })
+        };
+        tokio::task::yield_now().await;
+        // The object is reserved for the waiting `get` call
+        drop(obj);
+        let obj = get.await.unwrap();
+        assert_eq!(*obj, 1);
+        tokio::task::yield_now().await;
+        assert!(!get_matching.is_finished());
+        pool.close();
+        assert!(matches!(
+            get_matching.await.unwrap(),
+            Err(PoolError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_add_with_ttl() {
+        let pool = Pool::new(2);
+        pool.add(2).await.unwrap();
+        pool.add_with_ttl(1, Duration::from_millis(1))
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(10)).await;
+        // The expired object is discarded and frees its slot
+        assert_eq!(*pool.get().await.unwrap(), 2);
+        let status = pool.status();
+        assert_eq!(status.size, 1);
+        assert_eq!(status.available, 1);
+        pool.try_add(3).unwrap();
+        assert!(pool.try_add(4).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_validate() {
+        let pool = Pool::builder()
+            .max_size(2)
+            .validate(|obj: &i32| *obj > 0)
+            .build()
+            .unwrap();
+        pool.try_add(1).unwrap();
+        pool.try_add(0).unwrap();
+        assert_eq!(*pool.try_get().unwrap(), 1);
+        assert_eq!(pool.status().size, 1);
+        let mut obj = pool.get().await.unwrap();
+        *obj = -1;
+        drop(obj);
+        assert!(matches!(pool.try_get(), Err(PoolError::Timeout)));
+        let status = pool.status();
+        assert_eq!(status.size, 0);
+        assert_eq!(status.available, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_drain() {
+        let pool = Pool::from(vec![1, 2, 3]);
+        let obj0 = pool.get().await.unwrap();
+        let obj1 = pool.get().await.unwrap();
+        let (idle, mut drain) = pool.drain();
+        assert_eq!(idle, vec![1]);
+        assert!(pool.is_closed());
+        assert!(matches!(pool.try_get(), Err(PoolError::Closed)));
+        drop(obj0);
+        assert_eq!(drain.next().await, Some(3));
+        assert_eq!(Object::take(obj1), 2);
+        assert_eq!(drain.next().await, None);
+        assert_eq!(pool.status().size, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_unmanaged_drain_concurrent() {
+        let pool = Pool::from((0..16).collect::<Vec<_>>());
+        let handles = (0..16)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    while let Ok(obj) = pool.get().await {
+                        tokio::task::yield_now().await;
+                        drop(obj);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Objects returned from other threads while draining end up
+        // either in `idle` or in `drain` but never in both.
+        let (mut objs, mut drain) = pool.drain();
+        while let Some(obj) = drain.next().await {
+            objs.push(obj);
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        objs.sort();
+        assert_eq!(objs, (0..16).collect::<Vec<_>>());
+        assert_eq!(pool.status().size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_into_inner() {
+        let pool = Pool::from(vec![1, 2]);
+        let obj = pool.get().await.unwrap();
+        let pool = pool.into_inner().unwrap_err();
+        drop(obj);
+        let clone = pool.clone();
+        let pool = pool.into_inner().unwrap_err();
+        drop(clone);
+        let mut objs = match pool.into_inner() {
+            Ok(objs) => objs,
+            Err(_) => panic!("pool is uniquely owned"),
+        };
+        objs.sort();
+        assert_eq!(objs, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_from_iter_with_config() {
+        let pool = Pool::from_iter_with_config(vec![1, 2], &PoolConfig::new(3))
+            .ok()
+            .unwrap();
+        let status = pool.status();
+        assert_eq!(status.max_size, 3);
+        assert_eq!(status.size, 2);
+        pool.try_add(3).unwrap();
+        let (objs, err) = Pool::from_iter_with_config(vec![1, 2], &PoolConfig::new(1))
+            .err()
+            .unwrap();
+        assert_eq!(objs, vec![1, 2]);
+        assert!(matches!(err, PoolError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_add_many() {
+        let pool = Pool::new(3);
+        pool.add_many(vec![1, 2]).await.unwrap();
+        // Adding the batch would exceed `max_size`
+        let (objs, err) = pool.try_add_many(vec![3, 4]).unwrap_err();
+        assert_eq!(objs, vec![3, 4]);
+        assert!(matches!(err, PoolError::Timeout));
+        assert_eq!(pool.status().size, 2);
+        assert!(pool.add_many(vec![3, 4, 5, 6]).await.is_err());
+
+        let add_many = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.add_many(vec![3, 4]).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!add_many.is_finished());
+        assert_eq!(Object::take(pool.get().await.unwrap()), 2);
+        add_many.await.unwrap().unwrap();
+        let status = pool.status();
+        assert_eq!(status.size, 3);
+        assert_eq!(status.available, 3);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_get_many() {
+        let pool = Pool::from(vec![1, 2, 3]);
+        let mut objs = pool.get_many(2).await.unwrap();
+        assert_eq!(objs.len(), 2);
+        assert_eq!(pool.status().available, 1);
+        assert!(matches!(pool.get_many(4).await, Err(PoolError::Timeout)));
+        let get_many = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.get_many(2).await.map(|objs| objs.len()) })
+        };
+        tokio::task::yield_now().await;
+        assert!(!get_many.is_finished());
+        objs.pop();
+        assert_eq!(get_many.await.unwrap().unwrap(), 2);
+        drop(objs);
+        assert_eq!(pool.status().available, 3);
+    }
+
+    #[tokio::test]
+    async fn test_unmanaged_get_many_validate() {
+        let pool = Pool::builder()
+            .max_size(3)
+            .validate(|obj: &i32| *obj > 0)
+            .build()
+            .unwrap();
+        pool.try_add_many(vec![1, 0, 2]).unwrap();
+        let mut objs = pool
+            .get_many(2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|obj| **obj)
+            .collect::<Vec<_>>();
+        objs.sort();
+        assert_eq!(objs, vec![1, 2]);
+        let status = pool.status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 2);
+    }
+
+    #[test]
+    fn test_unmanaged_blocking_get() {
+        let pool = Pool::from(vec![1]);
+        let obj = pool.blocking_get().unwrap();
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let waiting = {
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                assert!(matches!(pool.try_get(), Err(PoolError::Timeout)));
+                barrier.wait();
+                *pool.blocking_get().unwrap()
+            })
+        };
+        // The object is returned once the other thread found the pool
+        // empty. It gets the object no matter if it is already waiting.
+        barrier.wait();
+        drop(obj);
+        assert_eq!(waiting.join().unwrap(), 1);
+        assert_eq!(pool.status().available, 1);
+        assert_eq!(
+            *pool
+                .blocking_timeout_get(Some(Duration::from_secs(0)))
+                .unwrap(),
+            1
+        );
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test]
+    async fn test_unmanaged_blocking_get_in_async_context() {
+        let pool = Pool::from(vec![1]);
+        assert!(matches!(
+            pool.blocking_get(),
+            Err(PoolError::BlockingInAsyncContext)
+        ));
+        assert_eq!(pool.status().available, 1);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unmanaged_blocking_get_in_spawn_blocking() {
+        let pool = Pool::from(vec![1]);
+        let obj = {
+            let pool = pool.clone();
+            tokio::task::spawn_blocking(move || pool.blocking_get().map(|obj| *obj))
+                .await
+                .unwrap()
+        };
+        assert_eq!(obj.unwrap(), 1);
+        assert_eq!(pool.status().available, 1);
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unmanaged_blocking_get_in_multi_thread_runtime() {
+        let pool = Pool::from(vec![1]);
+        assert_eq!(*pool.blocking_get().unwrap(), 1);
+        assert_eq!(pool.status().available, 1);
+    }
 }
@@ -44,11 +44,24 @@ mod tests {
         assert!(matches!(pool.get().await, Err(PoolError::Timeout)));
     }
 
+    async fn _test_unmanaged_timeout_from_iter(runtime: Runtime) {
+        let cfg = PoolConfig {
+            max_size: 2,
+            timeout: Some(Duration::from_millis(1)),
+            runtime,
+        };
+        let pool = Pool::from_iter_with_config(vec![()], &cfg).ok().unwrap();
+        let _obj = pool.get().await.unwrap();
+        assert!(matches!(pool.get().await, Err(PoolError::Timeout)));
+        pool.try_add(()).unwrap();
+    }
+
     #[cfg(feature = "rt_tokio_1")]
     #[tokio::test]
     async fn test_rt_tokio_1() {
         _test_unmanaged_timeout_get(Runtime::Tokio1).await;
         _test_unmanaged_timeout_config(Runtime::Tokio1).await;
+        _test_unmanaged_timeout_from_iter(Runtime::Tokio1).await;
     }
 
     #[cfg(feature = "rt_async-std_1")]
@@ -56,5 +69,6 @@ mod tests {
     async fn test_rt_async_std_1() {
         _test_unmanaged_timeout_get(Runtime::AsyncStd1).await;
         _test_unmanaged_timeout_config(Runtime::AsyncStd1).await;
+        _test_unmanaged_timeout_from_iter(Runtime::AsyncStd1).await;
     }
 }